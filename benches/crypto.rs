@@ -0,0 +1,21 @@
+#[macro_use]
+extern crate criterion;
+extern crate mazzaroth_rs;
+
+use criterion::{black_box, Criterion};
+use mazzaroth_rs::crypto::{Blake2b, Hasher, Keccak256, Sha256, Sha3_256};
+
+fn bench_hash<H: Hasher>(c: &mut Criterion, name: &str) {
+    let data = vec![7u8; 1024];
+    c.bench_function(name, |b| b.iter(|| H::hash(black_box(&data))));
+}
+
+fn bench_hashers(c: &mut Criterion) {
+    bench_hash::<Sha3_256>(c, "sha3_256 1KiB");
+    bench_hash::<Keccak256>(c, "keccak256 1KiB");
+    bench_hash::<Sha256>(c, "sha256 1KiB");
+    bench_hash::<Blake2b>(c, "blake2b 1KiB");
+}
+
+criterion_group!(benches, bench_hashers);
+criterion_main!(benches);
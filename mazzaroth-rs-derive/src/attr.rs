@@ -0,0 +1,42 @@
+//! Parsing of the attributes shared by the derivations
+
+use syn;
+use syn::Meta::NameValue;
+
+/// Returns true if the attribute is named `name`
+pub fn has_name(attr: &syn::Attribute, name: &str) -> bool {
+    attr.path.segments.len() == 1 && attr.path.segments[0].ident == name
+}
+
+/// Parse `#[name = "value"]`
+pub fn str_value(attrs: &[syn::Attribute], name: &str) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| has_name(attr, name))
+        .map(|attr| match attr.interpret_meta() {
+            Some(NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) => lit.value(),
+            _ => panic!(
+                "expected {} attribute to be a string: `{} = \"...\"`",
+                name, name
+            ),
+        })
+        .next()
+}
+
+/// Parse `#[name = N]` where `N` is a `u32`
+pub fn u32_value(attrs: &[syn::Attribute], name: &str) -> Option<u32> {
+    attrs
+        .iter()
+        .filter(|attr| has_name(attr, name))
+        .map(|attr| match attr.interpret_meta() {
+            Some(NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Int(ref lit),
+                ..
+            })) if lit.value() <= u64::from(u32::MAX) => lit.value() as u32,
+            _ => panic!("expected {} attribute to be a u32: `{} = N`", name, name),
+        })
+        .next()
+}
@@ -35,11 +35,12 @@ pub struct Function {
     pub codec: HashMap<String, String>,
 }
 
-/// Item within the trait, function or Readonly function
+/// Item within the trait, function, Readonly function or Migrate function
 /// Other can be const, type, macro, or verbatim
 pub enum TraitItem {
     Function(Function),
     Readonly(Function),
+    Migrate(Function),
     Other(syn::TraitItem),
 }
 
@@ -85,13 +86,20 @@ impl TraitItem {
                 // Look for codec attrs
                 let codec = parse_attribute_codec(&method_trait_item.attrs);
 
-                // If the function has the readonly attribute return as a Readonly function, else it is a regular function
+                // If the function has the readonly attribute return as a Readonly function,
+                // if it has the migrate attribute return as a Migrate function, else it is a regular function
                 if has_attribute(&method_trait_item.attrs, "readonly") {
                     TraitItem::Readonly(new_function(
                         method_trait_item.sig.ident.clone(),
                         method_trait_item.sig,
                         codec,
                     ))
+                } else if has_attribute(&method_trait_item.attrs, "migrate") {
+                    TraitItem::Migrate(new_function(
+                        method_trait_item.sig.ident.clone(),
+                        method_trait_item.sig,
+                        codec,
+                    ))
                 } else {
                     // Returns the TraitItem as a new Function
                     TraitItem::Function(new_function(
@@ -253,7 +261,7 @@ impl quote::ToTokens for TraitItem {
                     .into_token_stream(),
                 );
             }
-            TraitItem::Function(ref function) => {
+            TraitItem::Function(ref function) | TraitItem::Migrate(ref function) => {
                 tokens.append_all(
                     syn::TraitItem::Method(syn::TraitItemMethod {
                         attrs: Vec::new(),
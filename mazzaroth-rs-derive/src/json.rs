@@ -1,6 +1,6 @@
 //! JSON generation
 
-use mazzaroth_xdr::{Abi, FunctionSignature, Parameter, FunctionType};
+use mazzaroth_xdr::{Abi, FunctionSignature, FunctionType, Parameter};
 use xdr_rs_serialize::ser::XDROut;
 
 use contract;
@@ -131,7 +131,8 @@ impl<'a> From<&'a contract::Contract> for Abi {
         for item in intf.trait_items() {
             match *item {
                 // contract::Item::Event(ref event) => result.push(AbiEntry::Event(event.into())),
-                contract::TraitItem::Function(ref signature) => {
+                contract::TraitItem::Function(ref signature)
+                | contract::TraitItem::Migrate(ref signature) => {
                     let mut function: FunctionSignature = signature.into();
                     function.functionType = FunctionType::WRITE;
                     result.push(function)
@@ -145,7 +146,10 @@ impl<'a> From<&'a contract::Contract> for Abi {
             }
         }
 
-        Abi { version: VERSION.to_string(), functions: result }
+        Abi {
+            version: VERSION.to_string(),
+            functions: result,
+        }
     }
 }
 
@@ -216,6 +220,7 @@ fn push_canonicalized_primitive(target: &mut String, seg: &syn::PathSegment) {
         "i64" => target.push_str("int64"),
         "String" => target.push_str("string"),
         "bool" => target.push_str("bool"),
+        "PublicKey" | "Address" => target.push_str("address"),
         "Vec" => push_canonicalized_vec(target, &seg.arguments),
         _val => target.push_str("json"),
     }
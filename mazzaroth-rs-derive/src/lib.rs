@@ -80,8 +80,10 @@ extern crate xdr_rs_serialize;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 
+mod attr;
+
 mod contract;
-use contract::{Contract, TraitItem};
+use contract::{Contract, Function, TraitItem};
 
 mod error;
 use error::{ProcError, Result};
@@ -89,15 +91,26 @@ use error::{ProcError, Result};
 mod json;
 use json::write_json_abi;
 
+mod row;
+
+mod table;
+
+mod versioned;
+
 /// Macro used to mark the trait that defines the mazzaroth contract
 ///
 /// The argument becomes the module name used to construct the contract in main.
 ///
+/// Functions marked `#[readonly]` are exported as read functions. Functions marked
+/// `#[migrate]` are exported as write functions that can only be executed by the
+/// channel owner and are used to upgrade versioned state in place.
+///
 /// Example:
 /// ```ignore
 /// #[mazzaroth_abi(HelloWorld)]
 /// pub trait HelloWorldContract {
-///     
+///     #[migrate]
+///     fn upgrade(&mut self);
 /// }
 /// ```
 #[proc_macro_attribute]
@@ -113,6 +126,72 @@ pub fn mazzaroth_abi(args: TokenStream, input: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Derive macro implementing `FromRow` so query result rows can be mapped into a struct
+///
+/// Every field is read from the column with the same name, use `#[column = "name"]`
+/// to read a field from a differently named column.
+///
+/// Example:
+/// ```ignore
+/// #[derive(FromRow)]
+/// pub struct User {
+///     name: String,
+///     #[column = "user_age"]
+///     age: u64,
+/// }
+/// ```
+#[proc_macro_derive(FromRow, attributes(column))]
+pub fn from_row(input: TokenStream) -> TokenStream {
+    let input_toks = parse_macro_input!(input as syn::DeriveInput);
+    row::impl_from_row(input_toks).into()
+}
+
+/// Derive macro implementing `Table` so a struct can be inserted as a kvquery row
+///
+/// The table name defaults to the struct name and can be set with `#[table = "name"]`.
+/// Every field is stored in the column with the same name, use `#[column = "name"]`
+/// to store a field in a differently named column. Field types must implement `XDROut`.
+///
+/// Example:
+/// ```ignore
+/// #[derive(KqTable)]
+/// #[table = "users"]
+/// pub struct User {
+///     name: String,
+///     #[column = "user_age"]
+///     age: u64,
+/// }
+/// ```
+#[proc_macro_derive(KqTable, attributes(table, column))]
+pub fn kq_table(input: TokenStream) -> TokenStream {
+    let input_toks = parse_macro_input!(input as syn::DeriveInput);
+    table::impl_kq_table(input_toks).into()
+}
+
+/// Derive macro implementing `Versioned` so a type can be stored with a schema version
+///
+/// The version is set with `#[version = N]`. Every migration that upgrades stored
+/// values from version `N` to `N + 1` is declared with
+/// `#[migration(from = N, with = "function")]`, values stored before the type was
+/// versioned have version 0. The type must implement `XDRIn` and `XDROut`.
+///
+/// Example:
+/// ```ignore
+/// #[derive(XDRIn, XDROut, Versioned)]
+/// #[version = 2]
+/// #[migration(from = 0, with = "profile_v0_to_v1")]
+/// #[migration(from = 1, with = "profile_v1_to_v2")]
+/// pub struct Profile {
+///     name: String,
+///     age: u64,
+/// }
+/// ```
+#[proc_macro_derive(Versioned, attributes(version, migration))]
+pub fn versioned(input: TokenStream) -> TokenStream {
+    let input_toks = parse_macro_input!(input as syn::DeriveInput);
+    versioned::impl_versioned(input_toks).into()
+}
+
 fn impl_mazzaroth_abi(
     args: syn::AttributeArgs,
     input: syn::Item,
@@ -164,126 +243,143 @@ fn impl_mazzaroth_abi(
 fn tokenize_contract(name: &str, contract: &Contract) -> proc_macro2::TokenStream {
     // Loop through the trait items of the contract and for Functions build a
     // quote map of function name to a function wrapper that gets arguments from encoded bytes
-    // and returns bytes. Also includes Readonly functions in contract.
-    let functions: Vec<proc_macro2::TokenStream> = contract.trait_items().iter().filter_map(|item| {
-		match *item {
-			TraitItem::Function(ref function) => {
-				let function_ident = &function.name;
-
-                // Create a matchname string literal that matches name of function
-                let match_name = syn::Lit::Str(syn::LitStr::new(&function_ident.to_string(), Span::call_site()));
-
-                let arg_types = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
-                let arg_types2 = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
-                let ret_type = function.ret_types.iter().map(|ref ty| quote! {#ty}).next();
-
-                if function.ret_types.is_empty() {
-                    Some(quote! {
-                        #match_name => {
-                            inner.#function_ident(
-                                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
-                            );
-                            Ok(Vec::new())
-                        }
-                    })
-                } else {
-                    Some(quote! {
-                        #match_name => {
-                            let result = inner.#function_ident(
-                                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
-                            );
-                            let mut encoder = mazzaroth_rs::Encoder::default();
-                            encoder.push(result, stringify!(#ret_type));
-                            Ok(encoder.values())
-                        }
-                    })
-                }
-			},
-            TraitItem::Readonly(ref function) => {
-				let function_ident = &function.name;
-
-                // Create a matchname string literal that matches name of function
-                let match_name = syn::Lit::Str(syn::LitStr::new(&function_ident.to_string(), Span::call_site()));
-
-                let arg_types = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
-                let arg_types2 = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
-                let ret_type = function.ret_types.iter().map(|ref ty| quote! {#ty}).next();
-
-                if function.ret_types.is_empty() {
-                    Some(quote! {
-                        #match_name => {
-                            inner.#function_ident(
-                                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
-                            );
-                            Ok(Vec::new())
-                        }
-                    })
-                } else {
-                    Some(quote! {
-                        #match_name => {
-                            let result = inner.#function_ident(
-                                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
-                            );
-                            let mut encoder = mazzaroth_rs::Encoder::default();
-                            encoder.push(result, stringify!(#ret_type));
-                            Ok(encoder.values())
+    // and returns bytes. Also includes Readonly and Migrate functions in contract.
+    let functions: Vec<proc_macro2::TokenStream> = contract
+        .trait_items()
+        .iter()
+        .filter_map(|item| match *item {
+            TraitItem::Function(ref function) | TraitItem::Readonly(ref function) => {
+                let match_name = function_match_name(function);
+                let call = tokenize_function(function);
+                Some(quote! {
+                    #match_name => {
+                        #call
+                    }
+                })
+            }
+            TraitItem::Migrate(ref function) => {
+                // Migrations rewrite stored state so only the channel owner may call them
+                let match_name = function_match_name(function);
+                let call = tokenize_function(function);
+                Some(quote! {
+                    #match_name => {
+                        let sender = mazzaroth_rs::external::transaction::sender();
+                        if !mazzaroth_rs::external::account::is_owner(sender) {
+                            return Err(mazzaroth_rs::ContractError::unauthorized());
                         }
-                    })
-                }
-			},
-			_ => None,
-		}
-	}).collect();
+                        #call
+                    }
+                })
+            }
+            _ => None,
+        })
+        .collect();
 
     let endpoint_ident = syn::Ident::new(name, Span::call_site());
     let name_ident = syn::Ident::new(&contract.name(), Span::call_site());
 
     quote! {
-        pub struct #endpoint_ident<T: #name_ident> {
+        pub struct #endpoint_ident<T: #name_ident, H: mazzaroth_rs::Host + 'static = mazzaroth_rs::host::HostImpl> {
             pub inner: T,
+            host: ::std::rc::Rc<::std::cell::RefCell<H>>,
         }
 
         impl<T: #name_ident> From<T> for #endpoint_ident<T> {
             fn from(inner: T) -> #endpoint_ident<T> {
+                #endpoint_ident::new(inner)
+            }
+        }
+
+        impl<T: #name_ident> #endpoint_ident<T> {
+            pub fn new(inner: T) -> Self {
                 #endpoint_ident {
                     inner: inner,
+                    host: ::std::rc::Rc::new(::std::cell::RefCell::new(mazzaroth_rs::host::HostImpl::default())),
                 }
             }
         }
 
-        impl<T: #name_ident> #endpoint_ident<T> {
-            pub fn new(inner: T) -> Self {
+        impl<T: #name_ident, H: mazzaroth_rs::Host + 'static> #endpoint_ident<T, H> {
+            // Contract running against the given host, used to test contracts with a fake host
+            pub fn with_host(inner: T, host: H) -> Self {
                 #endpoint_ident {
                     inner: inner,
+                    host: ::std::rc::Rc::new(::std::cell::RefCell::new(host)),
                 }
             }
 
             pub fn instance(&self) -> &T {
                 &self.inner
             }
+
+            pub fn host(&self) -> ::std::cell::Ref<H> {
+                self.host.borrow()
+            }
+
+            pub fn host_mut(&mut self) -> ::std::cell::RefMut<H> {
+                self.host.borrow_mut()
+            }
         }
 
-        impl<T: #name_ident> mazzaroth_rs::ContractInterface for #endpoint_ident<T> {
+        impl<T: #name_ident, H: mazzaroth_rs::Host + 'static> mazzaroth_rs::ContractInterface for #endpoint_ident<T, H> {
             #[allow(unused_mut)]
             #[allow(unused_variables)]
             fn execute(&mut self, payload: &[u8]) -> Result<Vec<u8>, mazzaroth_rs::ContractError> {
                 let inner = &mut self.inner;
 
-                // first decode the call from stream
-                let mut payload_decoder = mazzaroth_rs::Decoder::new(payload);
-                match payload_decoder.pop::<mazzaroth_xdr::Call>() {
-                    Ok(call) => {
-                         // Then create a decoder for arguments
-                        let mut decoder = mazzaroth_rs::InputDecoder::new(&call.arguments);
-
-                        match call.function.as_str() {
-                            #(#functions,)*
-                            _ => Err(mazzaroth_rs::ContractError::invalid_function()),
-                        }
-                    },
-                    _ => Err(mazzaroth_rs::ContractError::invalid_function())
-                }
+                // The host is reachable with mazzaroth_rs::host::with_host while the call runs
+                mazzaroth_rs::host::scoped(self.host.clone(), move || {
+                    // first decode the call from stream
+                    let mut payload_decoder = mazzaroth_rs::Decoder::new(payload);
+                    match payload_decoder.pop::<mazzaroth_xdr::Call>() {
+                        Ok(call) => {
+                             // Then create a decoder for arguments
+                            let mut decoder = mazzaroth_rs::InputDecoder::new(&call.arguments);
+
+                            match call.function.as_str() {
+                                #(#functions,)*
+                                _ => Err(mazzaroth_rs::ContractError::invalid_function()),
+                            }
+                        },
+                        _ => Err(mazzaroth_rs::ContractError::invalid_function())
+                    }
+                })
             }
         }
     }
 }
+
+// Create a matchname string literal that matches name of function
+fn function_match_name(function: &Function) -> syn::Lit {
+    syn::Lit::Str(syn::LitStr::new(
+        &function.name.to_string(),
+        Span::call_site(),
+    ))
+}
+
+// Tokenize the call to a contract function, decoding its arguments and encoding its return
+fn tokenize_function(function: &Function) -> proc_macro2::TokenStream {
+    let function_ident = &function.name;
+
+    let arg_types = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
+    let arg_types2 = function.arguments.iter().map(|&(_, ref ty)| quote! { #ty });
+    let ret_type = function.ret_types.iter().map(|ref ty| quote! {#ty}).next();
+
+    if function.ret_types.is_empty() {
+        quote! {
+            inner.#function_ident(
+                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
+            );
+            Ok(Vec::new())
+        }
+    } else {
+        quote! {
+            let result = inner.#function_ident(
+                #(decoder.pop::<#arg_types>(stringify!(#arg_types2))?),*
+            );
+            let mut encoder = mazzaroth_rs::Encoder::default();
+            encoder.push(result, stringify!(#ret_type));
+            Ok(encoder.values())
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! FromRow derivation for mapping query result rows into structs
+
+use attr::str_value;
+use proc_macro2::Span;
+use syn;
+
+/// Implements `mazzaroth_rs::external::sql::FromRow` for a struct with named fields.
+///
+/// Each field is read from the column with the same name unless it is
+/// renamed with `#[column = "name"]`.
+pub fn impl_from_row(input: syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("FromRow can only be derived for structs with named fields"),
+    };
+
+    let field_idents = fields.iter().map(|field| field.ident.clone().unwrap());
+    let column_names = fields.iter().map(|field| {
+        let column =
+            column_name(&field.attrs).unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+        syn::LitStr::new(&column, Span::call_site())
+    });
+
+    quote! {
+        impl ::mazzaroth_rs::external::sql::FromRow for #name {
+            fn from_row(
+                row: &::mazzaroth_rs::external::sql::Row,
+            ) -> Result<Self, ::mazzaroth_rs::external::sql::SqlError> {
+                Ok(#name {
+                    #(#field_idents: row.get(#column_names)?,)*
+                })
+            }
+        }
+    }
+}
+
+/// Parse `#[column = "name"]`
+pub fn column_name(attrs: &[syn::Attribute]) -> Option<String> {
+    str_value(attrs, "column")
+}
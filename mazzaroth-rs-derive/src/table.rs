@@ -0,0 +1,65 @@
+//! KqTable derivation for declaring kvquery tables from structs
+
+use attr::str_value;
+use json::canonicalize_type;
+use proc_macro2::Span;
+use row::column_name;
+use syn;
+
+/// Implements `mazzaroth_rs::external::sql::Table` for a struct with named fields.
+///
+/// The table name is the struct name unless set with `#[table = "name"]`. Each
+/// field is stored in the column with the same name unless it is renamed with
+/// `#[column = "name"]`, column types are the canonical ABI types of the fields.
+pub fn impl_kq_table(input: syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("KqTable can only be derived for structs with named fields"),
+    };
+
+    let table_name = syn::LitStr::new(
+        &str_value(&input.attrs, "table").unwrap_or_else(|| name.to_string()),
+        Span::call_site(),
+    );
+
+    let columns: Vec<syn::LitStr> = fields
+        .iter()
+        .map(|field| {
+            let column = column_name(&field.attrs)
+                .unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+            syn::LitStr::new(&column, Span::call_site())
+        })
+        .collect();
+    let columns2 = columns.clone();
+    let column_types = fields
+        .iter()
+        .map(|field| syn::LitStr::new(&canonicalize_type(&field.ty), Span::call_site()));
+    let field_idents = fields.iter().map(|field| field.ident.clone().unwrap());
+
+    quote! {
+        impl ::mazzaroth_rs::external::sql::Table for #name {
+            fn table_name() -> &'static str {
+                #table_name
+            }
+
+            fn columns() -> Vec<::mazzaroth_rs::external::sql::Column> {
+                vec![
+                    #(::mazzaroth_rs::external::sql::Column {
+                        name: #columns,
+                        column_type: #column_types,
+                    },)*
+                ]
+            }
+
+            fn to_json(&self) -> Result<String, ::mazzaroth_rs::external::sql::WriteError> {
+                let mut object = ::mazzaroth_rs::external::sql::JsonObject::new();
+                #(object.field(#columns2, &self.#field_idents)?;)*
+                Ok(object.finish())
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Versioned derivation for declaring the schema version and migrations of stored types
+
+use attr::{has_name, u32_value};
+use proc_macro2::Span;
+use syn;
+use syn::Meta::{List, NameValue};
+
+/// Implements `mazzaroth_rs::schema::Versioned` for a type.
+///
+/// The version is set with `#[version = N]` and every migration from version
+/// `N` to `N + 1` is declared with `#[migration(from = N, with = "function")]`.
+pub fn impl_versioned(input: syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+
+    let version = u32_value(&input.attrs, "version")
+        .map(u64::from)
+        .unwrap_or_else(|| panic!("Versioned requires a version attribute: `version = N`"));
+    let (from, with): (Vec<syn::LitInt>, Vec<syn::Path>) = migrations(&input.attrs)
+        .into_iter()
+        .map(|(from, with)| {
+            if from >= version {
+                panic!(
+                    "migration from version {} is not below version {}",
+                    from, version
+                );
+            }
+            (
+                syn::LitInt::new(from, syn::IntSuffix::None, Span::call_site()),
+                with,
+            )
+        })
+        .unzip();
+    let version = syn::LitInt::new(version, syn::IntSuffix::None, Span::call_site());
+
+    quote! {
+        impl ::mazzaroth_rs::schema::Versioned for #name {
+            const VERSION: u32 = #version;
+
+            fn migrations() -> ::mazzaroth_rs::schema::Migrations {
+                ::mazzaroth_rs::schema::Migrations::new()
+                    #(.register(#from, #with))*
+            }
+        }
+    }
+}
+
+// Parse every `#[migration(from = N, with = "function")]`
+fn migrations(attrs: &[syn::Attribute]) -> Vec<(u64, syn::Path)> {
+    attrs
+        .iter()
+        .filter(|attr| has_name(attr, "migration"))
+        .map(|attr| {
+            let list = match attr.interpret_meta() {
+                Some(List(list)) => list,
+                _ => panic!(
+                    "expected migration attribute: `migration(from = N, with = \"function\")`"
+                ),
+            };
+            let mut from = None;
+            let mut with = None;
+            for nested in list.nested.iter() {
+                match nested {
+                    syn::NestedMeta::Meta(NameValue(syn::MetaNameValue {
+                        ref ident,
+                        lit: syn::Lit::Int(ref lit),
+                        ..
+                    })) if ident == "from" => from = Some(lit.value()),
+                    syn::NestedMeta::Meta(NameValue(syn::MetaNameValue {
+                        ref ident,
+                        lit: syn::Lit::Str(ref lit),
+                        ..
+                    })) if ident == "with" => {
+                        with = Some(syn::parse_str::<syn::Path>(&lit.value()).unwrap_or_else(
+                            |_| panic!("expected a function path: `with = \"function\"`"),
+                        ))
+                    }
+                    _ => panic!(
+                        "expected migration attribute: `migration(from = N, with = \"function\")`"
+                    ),
+                }
+            }
+            match (from, with) {
+                (Some(from), Some(with)) => (from, with),
+                _ => panic!(
+                    "expected migration attribute: `migration(from = N, with = \"function\")`"
+                ),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,193 @@
+//! Contracts generated with `mazzaroth_abi` driven through `TestRuntime` and a fake host
+
+extern crate mazzaroth_rs;
+extern crate mazzaroth_rs_derive;
+extern crate mazzaroth_xdr;
+extern crate xdr_rs_serialize;
+
+use mazzaroth_rs::external::sql::{ResultSet, SqlError, WriteError};
+use mazzaroth_rs::external::transaction::{TransactionInfo, CHANNEL_ID_LENGTH, HASH_LENGTH};
+use mazzaroth_rs::external::{persistence, ExternalError};
+use mazzaroth_rs::testing::{argument, CallError, TestRuntime};
+use mazzaroth_rs::{ContractError, ContractInterface, Host, PublicKey};
+use mazzaroth_rs_derive::mazzaroth_abi;
+use mazzaroth_xdr::Call;
+use std::collections::HashMap;
+use xdr_rs_serialize::ser::XDROut;
+
+#[mazzaroth_abi(Counter)]
+pub trait CounterContract {
+    fn add(&mut self, amount: u64) -> u64;
+
+    #[readonly]
+    fn count(&mut self) -> u64;
+
+    #[migrate]
+    fn reset(&mut self) -> u64;
+}
+
+pub struct Count {}
+
+const COUNT_KEY: &[u8] = b"count";
+
+fn stored_count() -> u64 {
+    match persistence::get(COUNT_KEY.to_vec()) {
+        Ok(bytes) => {
+            let mut count = [0u8; 8];
+            count.copy_from_slice(&bytes);
+            u64::from_be_bytes(count)
+        }
+        Err(_) => 0,
+    }
+}
+
+fn store_count(count: u64) {
+    persistence::store(COUNT_KEY.to_vec(), count.to_be_bytes().to_vec()).unwrap();
+}
+
+impl CounterContract for Count {
+    fn add(&mut self, amount: u64) -> u64 {
+        let count = stored_count() + amount;
+        store_count(count);
+        count
+    }
+
+    fn count(&mut self) -> u64 {
+        stored_count()
+    }
+
+    fn reset(&mut self) -> u64 {
+        let count = stored_count();
+        store_count(0);
+        count
+    }
+}
+
+#[test]
+fn test_runtime() {
+    let mut runtime = TestRuntime::new(Counter::new(Count {}));
+    assert_eq!(2, runtime.call("add", (2u64,)).decode::<u64>());
+    assert_eq!(5, runtime.call("add", (3u64,)).decode::<u64>());
+    assert_eq!(5, runtime.call("count", ()).decode::<u64>());
+    assert!(runtime.call("missing", ()).is_err());
+
+    // only the channel owner may call a migrate function
+    let alice = PublicKey::new([1u8; 32]);
+    let result = runtime.caller(alice).call("reset", ());
+    match result.error() {
+        Some(CallError::Contract(err)) => {
+            assert_eq!(ContractError::unauthorized().to_string(), err.to_string())
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(result.state_diff().is_empty());
+
+    let result = runtime.caller(alice).owner(true).call("reset", ());
+    assert_eq!(5, result.decode::<u64>());
+    assert_eq!(
+        Some(&0u64.to_be_bytes()[..]),
+        result.changed(COUNT_KEY).unwrap().after.as_deref()
+    );
+    assert_eq!(0, runtime.call("count", ()).decode::<u64>());
+}
+
+// Host keeping the state of a single contract, the sender is the owner if `owner` is set
+#[derive(Default)]
+struct FakeHost {
+    owner: bool,
+    state: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl TransactionInfo for FakeHost {
+    fn arguments(&self) -> Vec<u8> {
+        vec![]
+    }
+    fn ret(&self, _values: &[u8]) {}
+    fn sender(&self) -> PublicKey {
+        PublicKey::new([2u8; 32])
+    }
+    fn hash(&self) -> [u8; HASH_LENGTH] {
+        [0u8; HASH_LENGTH]
+    }
+    fn channel_id(&self) -> [u8; CHANNEL_ID_LENGTH] {
+        [0u8; CHANNEL_ID_LENGTH]
+    }
+    fn block_height(&self) -> u64 {
+        0
+    }
+    fn block_timestamp(&self) -> u64 {
+        0
+    }
+}
+
+impl Host for FakeHost {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>, ExternalError> {
+        self.state
+            .get(key)
+            .cloned()
+            .ok_or(ExternalError::MissingKeyError)
+    }
+    fn store(&mut self, key: &[u8], value: &[u8]) -> Result<(), ExternalError> {
+        self.state.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+    fn delete(&mut self, key: &[u8]) -> Result<(), ExternalError> {
+        self.state.remove(key);
+        Ok(())
+    }
+    fn query(&self, _query: &str) -> Result<ResultSet, SqlError> {
+        Err(SqlError::Host)
+    }
+    fn insert(&mut self, _table_name: &str, _json: &str) -> Result<(), WriteError> {
+        Ok(())
+    }
+    fn update(&mut self, _table_name: &str, _filter: &str, _json: &str) -> Result<u32, WriteError> {
+        Ok(0)
+    }
+    fn delete_rows(&mut self, _table_name: &str, _filter: &str) -> Result<u32, WriteError> {
+        Ok(0)
+    }
+    fn is_owner(&self, key: PublicKey) -> bool {
+        self.owner && key == self.sender()
+    }
+    fn account_name(&self, _key: PublicKey) -> Result<String, ExternalError> {
+        Err(ExternalError::MissingKeyError)
+    }
+    fn log(&mut self, _msg: &str) {}
+}
+
+fn call(function: &str, arguments: Vec<mazzaroth_xdr::Argument>) -> Vec<u8> {
+    let call = Call {
+        function: function.to_string(),
+        arguments,
+    };
+    let mut payload = Vec::new();
+    call.write_xdr(&mut payload).unwrap();
+    payload
+}
+
+#[test]
+fn test_with_host() {
+    let mut contract = Counter::with_host(Count {}, FakeHost::default());
+    assert_eq!(
+        b"4".to_vec(),
+        contract
+            .execute(&call("add", vec![argument(4u64)]))
+            .unwrap()
+    );
+    assert_eq!(
+        Some(&4u64.to_be_bytes().to_vec()),
+        contract.host().state.get(COUNT_KEY)
+    );
+
+    assert!(contract.execute(&call("reset", vec![])).is_err());
+    contract.host_mut().owner = true;
+    assert_eq!(
+        b"4".to_vec(),
+        contract.execute(&call("reset", vec![])).unwrap()
+    );
+    assert_eq!(
+        Some(&0u64.to_be_bytes().to_vec()),
+        contract.host().state.get(COUNT_KEY)
+    );
+}
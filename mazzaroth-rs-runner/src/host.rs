@@ -0,0 +1,549 @@
+//! Host functions imported by contracts, implemented over an in-memory state.
+//!
+//! Every function declared in the `externs` module of `mazzaroth-rs` is
+//! provided under the `env` module, with pointers and lengths passed as
+//! wasm32 `i32` values.
+
+use mazzaroth_rs::crypto;
+use mazzaroth_rs::PublicKey;
+use wasmi::{Caller, Error, Extern, Linker, Memory};
+
+use super::report::Report;
+use super::state::{Execution, State, Transaction};
+
+use std::collections::HashMap;
+
+/// Module the contract imports the host functions from
+pub const MODULE: &str = "env";
+
+const QUERY_HASH_LENGTH: usize = 16;
+
+// Return code of the sql write functions when the tables reject a write, the
+// host ABI only tells zero and non zero codes apart
+const WRITE_ERROR: u32 = 1;
+
+/// Data of the store a contract runs in.
+pub struct HostContext {
+    pub(crate) state: State,
+    pub(crate) transaction: Transaction,
+    pub(crate) execution: Execution,
+    // Query results waiting for `_kq_query_fetch`, by query hash
+    queries: HashMap<Vec<u8>, Vec<u8>>,
+    // Queries run so far, hashed with each query so repeated queries get distinct hashes
+    query_count: u64,
+}
+
+impl HostContext {
+    pub(crate) fn new(state: State, transaction: Transaction) -> Self {
+        HostContext {
+            state,
+            transaction,
+            execution: Execution::default(),
+            queries: HashMap::new(),
+            query_count: 0,
+        }
+    }
+}
+
+type HostCaller<'a> = Caller<'a, HostContext>;
+
+fn memory(caller: &HostCaller) -> Result<Memory, Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Error::new("contract does not export its memory"))
+}
+
+fn read(caller: &mut HostCaller, ptr: u32, len: u32) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![0u8; len as usize];
+    memory(caller)?
+        .read(&*caller, ptr as usize, &mut bytes)
+        .map_err(|err| Error::new(err.to_string()))?;
+    report(caller).memory_read += u64::from(len);
+    Ok(bytes)
+}
+
+fn read_string(caller: &mut HostCaller, ptr: u32, len: u32) -> Result<String, Error> {
+    String::from_utf8(read(caller, ptr, len)?).map_err(|_| Error::new("string is not valid UTF-8"))
+}
+
+fn read_key(caller: &mut HostCaller, ptr: u32, len: u32) -> Result<PublicKey, Error> {
+    PublicKey::from_slice(&read(caller, ptr, len)?).map_err(|err| Error::new(err.to_string()))
+}
+
+fn write(caller: &mut HostCaller, ptr: u32, bytes: &[u8]) -> Result<(), Error> {
+    memory(caller)?
+        .write(&mut *caller, ptr as usize, bytes)
+        .map_err(|err| Error::new(err.to_string()))?;
+    report(caller).memory_written += bytes.len() as u64;
+    Ok(())
+}
+
+fn context<'a>(caller: &'a mut HostCaller) -> &'a mut HostContext {
+    caller.data_mut()
+}
+
+fn report<'a>(caller: &'a mut HostCaller) -> &'a mut Report {
+    &mut caller.data_mut().execution.report
+}
+
+// Count a call of a host function in the report of the execution
+fn enter(caller: &mut HostCaller, name: &'static str) {
+    report(caller).host_call(name);
+}
+
+// Store a query result to be fetched with its hash, returns its length.
+//
+// The hash is the truncated sha3 of the query and the number of queries run
+// before it, written to the contract memory at `hash_ptr`.
+fn pending_query(
+    caller: &mut HostCaller,
+    query: &str,
+    hash_ptr: u32,
+    result: Option<Vec<u8>>,
+) -> Result<u32, Error> {
+    let result = match result {
+        Some(result) => result,
+        None => return Ok(0),
+    };
+    let count = context(caller).query_count;
+    context(caller).query_count += 1;
+    let mut data = query.as_bytes().to_vec();
+    data.extend_from_slice(&count.to_le_bytes());
+    let mut hash = crypto::sha3_256(&data);
+    hash.truncate(QUERY_HASH_LENGTH);
+    write(caller, hash_ptr, &hash)?;
+    let len = result.len() as u32;
+    context(caller).queries.insert(hash, result);
+    Ok(len)
+}
+
+/// Define every host function in the linker.
+pub fn link(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    link_transaction(linker)?;
+    link_persistence(linker)?;
+    link_account(linker)?;
+    link_log(linker)?;
+    link_sql(linker)?;
+    link_crypto(linker)?;
+    Ok(())
+}
+
+fn link_transaction(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(MODULE, "_input_length", |mut caller: HostCaller| {
+        enter(&mut caller, "_input_length");
+        caller.data().transaction.input.len() as u32
+    })?;
+    linker.func_wrap(
+        MODULE,
+        "_fetch_input",
+        |mut caller: HostCaller, ptr: u32| {
+            enter(&mut caller, "_fetch_input");
+            let input = caller.data().transaction.input.clone();
+            write(&mut caller, ptr, &input)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_fetch_sender",
+        |mut caller: HostCaller, ptr: u32| {
+            enter(&mut caller, "_fetch_sender");
+            let sender = caller.data().transaction.sender;
+            write(&mut caller, ptr, sender.as_bytes())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_fetch_transaction_hash",
+        |mut caller: HostCaller, ptr: u32| {
+            enter(&mut caller, "_fetch_transaction_hash");
+            let hash = caller.data().transaction.hash;
+            write(&mut caller, ptr, &hash)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_fetch_channel_id",
+        |mut caller: HostCaller, ptr: u32| {
+            enter(&mut caller, "_fetch_channel_id");
+            let channel_id = caller.data().transaction.channel_id;
+            write(&mut caller, ptr, &channel_id)
+        },
+    )?;
+    linker.func_wrap(MODULE, "_block_height", |mut caller: HostCaller| {
+        enter(&mut caller, "_block_height");
+        caller.data().transaction.block_height
+    })?;
+    linker.func_wrap(MODULE, "_block_timestamp", |mut caller: HostCaller| {
+        enter(&mut caller, "_block_timestamp");
+        caller.data().transaction.block_timestamp
+    })?;
+    linker.func_wrap(
+        MODULE,
+        "_ret",
+        |mut caller: HostCaller, ptr: u32, len: u32| {
+            enter(&mut caller, "_ret");
+            let values = read(&mut caller, ptr, len)?;
+            context(&mut caller).execution.ret = values;
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+fn link_persistence(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        "_store",
+        |mut caller: HostCaller, key: u32, key_len: u32, value: u32, value_len: u32| {
+            enter(&mut caller, "_store");
+            let key = read(&mut caller, key, key_len)?;
+            let value = read(&mut caller, value, value_len)?;
+            report(&mut caller).stored_bytes += (key.len() + value.len()) as u64;
+            context(&mut caller).state.values.insert(key, value);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_delete",
+        |mut caller: HostCaller, key: u32, key_len: u32| {
+            enter(&mut caller, "_delete");
+            let key = read(&mut caller, key, key_len)?;
+            context(&mut caller).state.values.remove(&key);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_get",
+        |mut caller: HostCaller, key: u32, key_len: u32, value: u32| {
+            enter(&mut caller, "_get");
+            let key = read(&mut caller, key, key_len)?;
+            let stored = match caller.data().state.values.get(&key) {
+                Some(stored) => stored.clone(),
+                None => return Err(Error::new("_get called with a missing key")),
+            };
+            report(&mut caller).loaded_bytes += stored.len() as u64;
+            write(&mut caller, value, &stored)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_get_length",
+        |mut caller: HostCaller, key: u32, key_len: u32| {
+            enter(&mut caller, "_get_length");
+            let key = read(&mut caller, key, key_len)?;
+            Ok(caller
+                .data()
+                .state
+                .values
+                .get(&key)
+                .map_or(0, |value| value.len() as u32))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_get_range",
+        |mut caller: HostCaller,
+         key: u32,
+         key_len: u32,
+         offset: u32,
+         value: u32,
+         value_len: u32| {
+            enter(&mut caller, "_get_range");
+            let key = read(&mut caller, key, key_len)?;
+            let range = match caller.data().state.values.get(&key) {
+                Some(stored) => {
+                    let start = (offset as usize).min(stored.len());
+                    let end = start.saturating_add(value_len as usize).min(stored.len());
+                    stored[start..end].to_vec()
+                }
+                None => vec![],
+            };
+            report(&mut caller).loaded_bytes += range.len() as u64;
+            write(&mut caller, value, &range)?;
+            Ok(range.len() as u32)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_key_exists",
+        |mut caller: HostCaller, key: u32, key_len: u32| {
+            enter(&mut caller, "_key_exists");
+            let key = read(&mut caller, key, key_len)?;
+            Ok(caller.data().state.values.contains_key(&key) as u32)
+        },
+    )?;
+    Ok(())
+}
+
+fn link_account(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        "_get_account_name_length",
+        |mut caller: HostCaller, key: u32, key_len: u32| {
+            enter(&mut caller, "_get_account_name_length");
+            let key = read_key(&mut caller, key, key_len)?;
+            Ok(caller
+                .data()
+                .state
+                .accounts
+                .get(&key)
+                .map_or(0, |name| name.len() as u32))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_get_account_name",
+        |mut caller: HostCaller, key: u32, key_len: u32, name: u32| {
+            enter(&mut caller, "_get_account_name");
+            let key = read_key(&mut caller, key, key_len)?;
+            let account = caller
+                .data()
+                .state
+                .accounts
+                .get(&key)
+                .cloned()
+                .unwrap_or_default();
+            write(&mut caller, name, account.as_bytes())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_is_owner",
+        |mut caller: HostCaller, key: u32, key_len: u32| {
+            enter(&mut caller, "_is_owner");
+            let key = read_key(&mut caller, key, key_len)?;
+            Ok((caller.data().state.owner == Some(key)) as u32)
+        },
+    )?;
+    Ok(())
+}
+
+fn link_log(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        "_log",
+        |mut caller: HostCaller, ptr: u32, len: u32| {
+            enter(&mut caller, "_log");
+            let msg = String::from_utf8_lossy(&read(&mut caller, ptr, len)?).into_owned();
+            context(&mut caller).execution.logs.push(msg);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_log_error",
+        |mut caller: HostCaller, ptr: u32, len: u32| {
+            enter(&mut caller, "_log_error");
+            let msg = String::from_utf8_lossy(&read(&mut caller, ptr, len)?).into_owned();
+            context(&mut caller).execution.errors.push(msg);
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+fn link_sql(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        "_kq_json_insert",
+        |mut caller: HostCaller, table: u32, table_len: u32, json: u32, json_len: u32| {
+            enter(&mut caller, "_kq_json_insert");
+            let table = read_string(&mut caller, table, table_len)?;
+            let json = read_string(&mut caller, json, json_len)?;
+            Ok(
+                match context(&mut caller).state.tables.insert(&table, &json) {
+                    Ok(()) => 0,
+                    Err(_) => WRITE_ERROR,
+                },
+            )
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_kq_json_update",
+        |mut caller: HostCaller,
+         table: u32,
+         table_len: u32,
+         filter: u32,
+         filter_len: u32,
+         json: u32,
+         json_len: u32,
+         affected: u32| {
+            enter(&mut caller, "_kq_json_update");
+            let table = read_string(&mut caller, table, table_len)?;
+            let filter = read_string(&mut caller, filter, filter_len)?;
+            let json = read_string(&mut caller, json, json_len)?;
+            let result = context(&mut caller)
+                .state
+                .tables
+                .update(&table, &filter, &json);
+            match result {
+                Ok(count) => {
+                    write(&mut caller, affected, &count.to_le_bytes())?;
+                    Ok(0)
+                }
+                Err(_) => Ok(WRITE_ERROR),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_kq_delete",
+        |mut caller: HostCaller,
+         table: u32,
+         table_len: u32,
+         filter: u32,
+         filter_len: u32,
+         affected: u32| {
+            enter(&mut caller, "_kq_delete");
+            let table = read_string(&mut caller, table, table_len)?;
+            let filter = read_string(&mut caller, filter, filter_len)?;
+            let result = context(&mut caller).state.tables.delete(&table, &filter);
+            match result {
+                Ok(count) => {
+                    write(&mut caller, affected, &count.to_le_bytes())?;
+                    Ok(0)
+                }
+                Err(_) => Ok(WRITE_ERROR),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_kq_query_run",
+        |mut caller: HostCaller, query: u32, query_len: u32, hash: u32| {
+            enter(&mut caller, "_kq_query_run");
+            let query = read_string(&mut caller, query, query_len)?;
+            report(&mut caller).query_bytes += query.len() as u64;
+            let result = caller
+                .data()
+                .state
+                .tables
+                .query(&query)
+                .ok()
+                .and_then(|result| result.to_xdr().ok());
+            pending_query(&mut caller, &query, hash, result)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_kq_query_page_run",
+        |mut caller: HostCaller,
+         query: u32,
+         query_len: u32,
+         token: u32,
+         token_len: u32,
+         page_size: u32,
+         hash: u32| {
+            enter(&mut caller, "_kq_query_page_run");
+            let query = read_string(&mut caller, query, query_len)?;
+            let token = read(&mut caller, token, token_len)?;
+            report(&mut caller).query_bytes += query.len() as u64;
+            let result = caller
+                .data()
+                .state
+                .tables
+                .query_page(&query, &token, page_size)
+                .ok()
+                .and_then(|page| page.to_xdr().ok());
+            pending_query(&mut caller, &query, hash, result)
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_kq_query_fetch",
+        |mut caller: HostCaller, result: u32, hash: u32| {
+            enter(&mut caller, "_kq_query_fetch");
+            let hash = read(&mut caller, hash, QUERY_HASH_LENGTH as u32)?;
+            match context(&mut caller).queries.remove(&hash) {
+                Some(bytes) => {
+                    report(&mut caller).result_bytes += bytes.len() as u64;
+                    write(&mut caller, result, &bytes)
+                }
+                None => Err(Error::new("_kq_query_fetch called without a query result")),
+            }
+        },
+    )?;
+    Ok(())
+}
+
+fn link_crypto(linker: &mut Linker<HostContext>) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        "_generate_key_pair",
+        |mut caller: HostCaller, private_key: u32, public_key: u32| {
+            enter(&mut caller, "_generate_key_pair");
+            match crypto::generate_key_pair() {
+                Ok(pair) => {
+                    write(&mut caller, private_key, &pair.private_key)?;
+                    write(&mut caller, public_key, pair.public_key.as_bytes())?;
+                    Ok(1u32)
+                }
+                Err(_) => Ok(0),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_sign_message",
+        |mut caller: HostCaller,
+         private_key: u32,
+         private_key_len: u32,
+         message: u32,
+         message_len: u32,
+         signature: u32| {
+            enter(&mut caller, "_sign_message");
+            let private_key = read(&mut caller, private_key, private_key_len)?;
+            let message = read(&mut caller, message, message_len)?;
+            match crypto::sign_message(&private_key, &message) {
+                Ok(signed) => {
+                    write(&mut caller, signature, &signed)?;
+                    Ok(1u32)
+                }
+                Err(_) => Ok(0),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "_verify_signature",
+        |mut caller: HostCaller,
+         public_key: u32,
+         public_key_len: u32,
+         message: u32,
+         message_len: u32,
+         signature: u32,
+         signature_len: u32| {
+            enter(&mut caller, "_verify_signature");
+            let public_key = read(&mut caller, public_key, public_key_len)?;
+            let message = read(&mut caller, message, message_len)?;
+            let signature = read(&mut caller, signature, signature_len)?;
+            Ok(crypto::verify(&public_key, &message, &signature).unwrap_or(false) as u32)
+        },
+    )?;
+    link_hash(linker, "_sha3_256", crypto::sha3_256)?;
+    link_hash(linker, "_keccak256", crypto::keccak256)?;
+    link_hash(linker, "_sha256", crypto::sha256)?;
+    link_hash(linker, "_blake2b", crypto::blake2b)?;
+    Ok(())
+}
+
+fn link_hash(
+    linker: &mut Linker<HostContext>,
+    name: &'static str,
+    hash: fn(&[u8]) -> Vec<u8>,
+) -> Result<(), Error> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: HostCaller, data: u32, data_len: u32, digest: u32| {
+            enter(&mut caller, name);
+            let data = read(&mut caller, data, data_len)?;
+            write(&mut caller, digest, &hash(&data))
+        },
+    )?;
+    Ok(())
+}
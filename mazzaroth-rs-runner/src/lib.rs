@@ -0,0 +1,448 @@
+//! # Mazzaroth Contract Runner
+//!
+//! Runs compiled contract `.wasm` artifacts locally with the
+//! [wasmi](https://crates.io/crates/wasmi) interpreter. Every host function a
+//! contract built with `mazzaroth-rs` can import is implemented over an
+//! in-memory `State`, so integration tests exercise the same code path as the
+//! Mazzaroth VM, including the wasm side of the host ABI.
+//!
+//! ```ignore
+//! use mazzaroth_rs::testing::argument;
+//! use mazzaroth_rs_runner::{Runner, Transaction};
+//!
+//! let mut runner = Runner::from_file("target/wasm32-unknown-unknown/release/hello.wasm")?;
+//! let execution = runner.call(Transaction::call("hello", vec![]).sender(alice))?;
+//! assert!(execution.is_ok());
+//! assert_eq!(b"14".to_vec(), execution.ret);
+//! ```
+
+pub mod host;
+mod report;
+mod state;
+
+pub use self::host::HostContext;
+pub use self::report::Report;
+pub use self::state::{Execution, State, Transaction};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use wasmi::core::TrapCode;
+use wasmi::{Config, Engine, Linker, Module, Store};
+
+/// Function called for every transaction unless `Runner::entry_point` is set
+pub const ENTRY_POINT: &str = "main";
+
+/// Defines the errors that can be returned when loading or instantiating a contract.
+#[derive(Debug)]
+pub enum RunnerError {
+    /// The contract file could not be read
+    Io(io::Error),
+    /// The module is invalid, imports an unknown function or does not export its entry point
+    Wasm(wasmi::Error),
+}
+
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunnerError::Io(err) => write!(f, "Could not read contract: {}", err),
+            RunnerError::Wasm(err) => write!(f, "Invalid contract: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RunnerError {}
+
+impl From<io::Error> for RunnerError {
+    fn from(err: io::Error) -> Self {
+        RunnerError::Io(err)
+    }
+}
+
+impl From<wasmi::Error> for RunnerError {
+    fn from(err: wasmi::Error) -> Self {
+        RunnerError::Wasm(err)
+    }
+}
+
+/// A compiled contract and the state it runs against.
+pub struct Runner {
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostContext>,
+    entry_point: String,
+    fuel_limit: Option<u64>,
+    state: State,
+}
+
+impl Runner {
+    /// Compile a contract from its wasm bytes
+    pub fn new(wasm: &[u8]) -> Result<Self, RunnerError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+        let mut linker = Linker::new(&engine);
+        host::link(&mut linker)?;
+        Ok(Runner {
+            engine,
+            module,
+            linker,
+            entry_point: ENTRY_POINT.to_string(),
+            fuel_limit: None,
+            state: State::default(),
+        })
+    }
+
+    /// Compile a contract from a `.wasm` file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, RunnerError> {
+        Runner::new(&fs::read(path)?)
+    }
+
+    /// Set the exported function called for every transaction, `main` by default
+    pub fn entry_point(mut self, name: &str) -> Self {
+        self.entry_point = name.to_string();
+        self
+    }
+
+    /// Abort calls that execute more than `limit` instructions, like the fuel
+    /// budget of a transaction
+    pub fn fuel_limit(mut self, limit: u64) -> Self {
+        self.fuel_limit = Some(limit);
+        self
+    }
+
+    /// Returns the state the next call runs against
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the state to set up accounts, the owner or values before a call
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    /// Run the entry point of a fresh instance of the contract for a transaction.
+    ///
+    /// The state is updated if the entry point returns, and left unchanged if the
+    /// contract traps or runs out of fuel, also in the start function of the
+    /// module. The instructions executed count from the instantiation of the
+    /// contract.
+    ///
+    /// # Returns
+    ///
+    /// Result<Execution, RunnerError>
+    /// * `Ok(Execution)` - the values returned and logged by the call and why it trapped
+    /// * `Err(RunnerError)` - the contract could not be instantiated
+    pub fn call(&mut self, transaction: Transaction) -> Result<Execution, RunnerError> {
+        let context = HostContext::new(self.state.clone(), transaction);
+        let mut store = Store::new(&self.engine, context);
+        let fuel = self.fuel_limit.unwrap_or(u64::MAX);
+        store
+            .set_fuel(fuel)
+            .map_err(|err| wasmi::Error::new(err.to_string()))?;
+        let instance = self.linker.instantiate(&mut store, &self.module)?;
+        // a trap in the start function of the module aborts the call like a trap in main
+        let trap = match instance.start(&mut store) {
+            Ok(instance) => {
+                let main = instance.get_typed_func::<(), ()>(&store, &self.entry_point)?;
+                main.call(&mut store, ()).err()
+            }
+            Err(trap) => Some(trap),
+        };
+        let remaining = store.get_fuel().unwrap_or(0);
+
+        let context = store.into_data();
+        let mut execution = context.execution;
+        execution.report.instructions = fuel - remaining;
+        match trap {
+            Some(trap) => {
+                execution.report.out_of_fuel = trap.as_trap_code() == Some(TrapCode::OutOfFuel);
+                execution.trap = Some(trap.to_string());
+            }
+            None => self.state = context.state,
+        }
+        Ok(execution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazzaroth_rs::PublicKey;
+
+    // Stores its input under "input", logs and returns it
+    const ECHO: &str = r#"
+        (module
+          (import "env" "_input_length" (func $input_length (result i32)))
+          (import "env" "_fetch_input" (func $fetch_input (param i32)))
+          (import "env" "_store" (func $store (param i32 i32 i32 i32)))
+          (import "env" "_log" (func $log (param i32 i32)))
+          (import "env" "_ret" (func $ret (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "input")
+          (data (i32.const 16) "stored")
+          (func (export "main")
+            (local $len i32)
+            (local.set $len (call $input_length))
+            (call $fetch_input (i32.const 1024))
+            (call $store (i32.const 0) (i32.const 5) (i32.const 1024) (local.get $len))
+            (call $log (i32.const 16) (i32.const 6))
+            (call $ret (i32.const 1024) (local.get $len))))
+    "#;
+
+    // Returns the sender if it is the owner, aborts otherwise
+    const OWNER: &str = r#"
+        (module
+          (import "env" "_fetch_sender" (func $fetch_sender (param i32)))
+          (import "env" "_is_owner" (func $is_owner (param i32 i32) (result i32)))
+          (import "env" "_store" (func $store (param i32 i32 i32 i32)))
+          (import "env" "_log_error" (func $log_error (param i32 i32)))
+          (import "env" "_ret" (func $ret (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "not the owner")
+          (data (i32.const 16) "touched")
+          (func (export "main")
+            (call $fetch_sender (i32.const 64))
+            (call $store (i32.const 16) (i32.const 7) (i32.const 64) (i32.const 32))
+            (if (i32.eqz (call $is_owner (i32.const 64) (i32.const 32)))
+              (then
+                (call $log_error (i32.const 0) (i32.const 13))
+                (unreachable)))
+            (call $ret (i32.const 64) (i32.const 32))))
+    "#;
+
+    // Inserts a row and returns the XDR result of a query of the table
+    const SQL: &str = r#"
+        (module
+          (import "env" "_kq_json_insert" (func $insert (param i32 i32 i32 i32) (result i32)))
+          (import "env" "_kq_query_run" (func $run (param i32 i32 i32) (result i32)))
+          (import "env" "_kq_query_fetch" (func $fetch (param i32 i32)))
+          (import "env" "_ret" (func $ret (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "users")
+          (data (i32.const 16) "{\"name\":\"bob\"}")
+          (data (i32.const 64) "SELECT * FROM users")
+          (func (export "main")
+            (local $len i32)
+            (drop (call $insert (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 14)))
+            (local.set $len (call $run (i32.const 64) (i32.const 19) (i32.const 128)))
+            (call $fetch (i32.const 1024) (i32.const 128))
+            (call $ret (i32.const 1024) (local.get $len))))
+    "#;
+
+    // Runs queries of two tables, then fetches and stores their results in reverse order
+    const INTERLEAVED: &str = r#"
+        (module
+          (import "env" "_kq_json_insert" (func $insert (param i32 i32 i32 i32) (result i32)))
+          (import "env" "_kq_query_run" (func $run (param i32 i32 i32) (result i32)))
+          (import "env" "_kq_query_fetch" (func $fetch (param i32 i32)))
+          (import "env" "_store" (func $store (param i32 i32 i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "users")
+          (data (i32.const 8) "pets")
+          (data (i32.const 16) "{\"name\":\"bob\"}")
+          (data (i32.const 32) "{\"name\":\"rex\"}")
+          (data (i32.const 64) "SELECT * FROM users")
+          (data (i32.const 96) "SELECT * FROM pets")
+          (data (i32.const 120) "ab")
+          (func (export "main")
+            (local $users i32)
+            (local $pets i32)
+            (drop (call $insert (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 14)))
+            (drop (call $insert (i32.const 8) (i32.const 4) (i32.const 32) (i32.const 14)))
+            (local.set $users (call $run (i32.const 64) (i32.const 19) (i32.const 128)))
+            (local.set $pets (call $run (i32.const 96) (i32.const 18) (i32.const 144)))
+            (call $fetch (i32.const 2048) (i32.const 144))
+            (call $fetch (i32.const 1024) (i32.const 128))
+            (call $store (i32.const 120) (i32.const 1) (i32.const 1024) (local.get $users))
+            (call $store (i32.const 121) (i32.const 1) (i32.const 2048) (local.get $pets))))
+    "#;
+
+    // Stores a value, then loops the number of times given by the first byte of input
+    const LOOP: &str = r#"
+        (module
+          (import "env" "_fetch_input" (func $fetch_input (param i32)))
+          (import "env" "_store" (func $store (param i32 i32 i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "key")
+          (func (export "main")
+            (local $n i32)
+            (call $fetch_input (i32.const 64))
+            (call $store (i32.const 0) (i32.const 3) (i32.const 0) (i32.const 3))
+            (local.set $n (i32.load8_u (i32.const 64)))
+            (block $done
+              (loop $next
+                (br_if $done (i32.eqz (local.get $n)))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br $next)))))
+    "#;
+
+    // Stores a value in its start function, then traps or loops until out of fuel
+    const START: &str = r#"
+        (module
+          (import "env" "_store" (func $store (param i32 i32 i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "key")
+          (func $start
+            (call $store (i32.const 0) (i32.const 3) (i32.const 0) (i32.const 3))
+            (loop $forever (br $forever)))
+          (start $start)
+          (func (export "main")))
+    "#;
+
+    fn runner(wat: &str) -> Runner {
+        Runner::new(&wat::parse_str(wat).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_call() {
+        let mut runner = runner(ECHO);
+        let execution = runner.call(Transaction::new(b"hello".to_vec())).unwrap();
+        assert!(execution.is_ok());
+        assert_eq!(b"hello".to_vec(), execution.ret);
+        assert_eq!(vec!["stored".to_string()], execution.logs);
+        assert_eq!(
+            Some(&b"hello".to_vec()),
+            runner.state().values.get(b"input".as_slice())
+        );
+    }
+
+    #[test]
+    fn test_trap_keeps_state() {
+        let owner = PublicKey::new([1u8; 32]);
+        let mut runner = runner(OWNER);
+        runner.state_mut().owner = Some(owner);
+
+        let execution = runner
+            .call(Transaction::default().sender(PublicKey::new([2u8; 32])))
+            .unwrap();
+        assert!(!execution.is_ok());
+        assert_eq!(vec!["not the owner".to_string()], execution.errors);
+        assert!(runner.state().values.is_empty());
+
+        let execution = runner.call(Transaction::default().sender(owner)).unwrap();
+        assert!(execution.is_ok());
+        assert_eq!(owner.to_vec(), execution.ret);
+        assert_eq!(1, runner.state().values.len());
+    }
+
+    #[test]
+    fn test_sql() {
+        use mazzaroth_rs::external::sql::ResultSet;
+
+        let mut runner = runner(SQL);
+        let execution = runner.call(Transaction::default()).unwrap();
+        let result = ResultSet::from_xdr(&execution.ret).unwrap();
+        assert_eq!(1, result.len());
+        assert_eq!(1, runner.state().tables.rows("users").len());
+    }
+
+    #[test]
+    fn test_interleaved_queries() {
+        use mazzaroth_rs::external::sql::ResultSet;
+
+        let mut runner = runner(INTERLEAVED);
+        assert!(runner.call(Transaction::default()).unwrap().is_ok());
+        let name = |key: &[u8]| {
+            let result = ResultSet::from_xdr(&runner.state().values[key]).unwrap();
+            assert_eq!(1, result.len());
+            result.row(0).unwrap().get::<String>("name").unwrap()
+        };
+        assert_eq!("bob", name(b"a"));
+        assert_eq!("rex", name(b"b"));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut echo = runner(ECHO);
+        let report = echo
+            .call(Transaction::new(b"hello".to_vec()))
+            .unwrap()
+            .report;
+        assert!(report.instructions > 0);
+        assert_eq!(5, report.total_calls());
+        assert_eq!(1, report.calls("_store"));
+        assert_eq!(0, report.calls("_get"));
+        assert_eq!(10, report.stored_bytes);
+        // key, value, log and return value
+        assert_eq!(5 + 5 + 6 + 5, report.memory_read);
+        assert_eq!(5, report.memory_written);
+        assert!(!report.out_of_fuel);
+
+        let report = echo.call(Transaction::default()).unwrap().report;
+        assert_eq!(5, report.stored_bytes);
+
+        let execution = runner(SQL).call(Transaction::default()).unwrap();
+        assert_eq!(19, execution.report.query_bytes);
+        assert_eq!(execution.ret.len() as u64, execution.report.result_bytes);
+    }
+
+    #[test]
+    fn test_instructions() {
+        let mut runner = runner(LOOP);
+        let short = runner.call(Transaction::new(vec![1])).unwrap().report;
+        let long = runner.call(Transaction::new(vec![100])).unwrap().report;
+        assert!(long.instructions > short.instructions + 99);
+    }
+
+    #[test]
+    fn test_fuel_limit() {
+        let limit = runner(LOOP)
+            .call(Transaction::new(vec![10]))
+            .unwrap()
+            .report
+            .instructions;
+
+        let mut runner = runner(LOOP).fuel_limit(limit);
+        assert!(runner.call(Transaction::new(vec![10])).unwrap().is_ok());
+        runner.state_mut().values.clear();
+
+        let execution = runner.call(Transaction::new(vec![11])).unwrap();
+        assert!(!execution.is_ok());
+        assert!(execution.report.out_of_fuel);
+        assert_eq!(limit, execution.report.instructions);
+        assert_eq!(1, execution.report.calls("_store"));
+        assert!(runner.state().values.is_empty());
+    }
+
+    #[test]
+    fn test_start_trap() {
+        let mut looping = runner(START).fuel_limit(10_000);
+        let execution = looping.call(Transaction::default()).unwrap();
+        assert!(execution.trap.is_some());
+        assert!(execution.report.out_of_fuel);
+        assert_eq!(1, execution.report.calls("_store"));
+        assert!(looping.state().values.is_empty());
+
+        let mut runner = runner(&START.replace("(loop $forever (br $forever))", "unreachable"));
+        let execution = runner.call(Transaction::default()).unwrap();
+        assert!(execution.trap.is_some());
+        assert!(!execution.report.out_of_fuel);
+        assert!(runner.state().values.is_empty());
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let wasm = wat::parse_str(ECHO.replace("\"main\"", "\"entry\"")).unwrap();
+        let mut runner = Runner::new(&wasm).unwrap();
+        assert!(runner.call(Transaction::default()).is_err());
+
+        let mut runner = Runner::new(&wasm).unwrap().entry_point("entry");
+        assert!(runner.call(Transaction::default()).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_import() {
+        let wasm = wat::parse_str(
+            r#"(module
+                 (import "env" "_missing" (func))
+                 (memory (export "memory") 1)
+                 (func (export "main")))"#,
+        )
+        .unwrap();
+        let mut runner = Runner::new(&wasm).unwrap();
+        assert!(runner.call(Transaction::default()).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Run a single call of a compiled contract against an empty in-memory state.
+//!
+//! ```console
+//! mazzaroth-run [--entry <name>] [--sender <hex>] [--owner] [--fuel <limit>] [--report] <contract.wasm> <function> [argument ...]
+//! ```
+//!
+//! Arguments are passed as they are encoded in a `mazzaroth_xdr::Argument`.
+//! Logs and errors are printed to stderr and the return value to stdout.
+//! `--report` prints what the call cost to stderr, and `--fuel` aborts the
+//! call after the given number of instructions.
+
+use mazzaroth_rs::PublicKey;
+use mazzaroth_rs_runner::{Runner, Transaction, ENTRY_POINT};
+use mazzaroth_xdr::Argument;
+
+use std::env;
+use std::process;
+
+const USAGE: &str =
+    "usage: mazzaroth-run [--entry <name>] [--sender <hex>] [--owner] [--fuel <limit>] [--report] <contract.wasm> <function> [argument ...]";
+
+struct Options {
+    entry_point: String,
+    sender: PublicKey,
+    owner: bool,
+    fuel_limit: Option<u64>,
+    report: bool,
+    positional: Vec<String>,
+}
+
+fn parse(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        entry_point: ENTRY_POINT.to_string(),
+        sender: PublicKey::default(),
+        owner: false,
+        fuel_limit: None,
+        report: false,
+        positional: vec![],
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--entry" => {
+                options.entry_point = args.next().ok_or("--entry requires a function name")?;
+            }
+            "--sender" => {
+                let hex = args.next().ok_or("--sender requires a public key")?;
+                options.sender = PublicKey::from_hex(&hex).map_err(|err| err.to_string())?;
+            }
+            "--owner" => options.owner = true,
+            "--fuel" => {
+                let limit = args.next().ok_or("--fuel requires a limit")?;
+                options.fuel_limit = Some(limit.parse().map_err(|_| "invalid fuel limit")?);
+            }
+            "--report" => options.report = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => options.positional.push(arg),
+        }
+    }
+    if options.positional.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    Ok(options)
+}
+
+fn run(options: Options) -> Result<bool, String> {
+    let mut positional = options.positional.into_iter();
+    let path = positional.next().unwrap();
+    let function = positional.next().unwrap();
+    let arguments = positional.map(|t| Argument { t }).collect();
+
+    let mut runner = Runner::from_file(&path)
+        .map_err(|err| err.to_string())?
+        .entry_point(&options.entry_point);
+    if let Some(limit) = options.fuel_limit {
+        runner = runner.fuel_limit(limit);
+    }
+    if options.owner {
+        runner.state_mut().owner = Some(options.sender);
+    }
+    let execution = runner
+        .call(Transaction::call(&function, arguments).sender(options.sender))
+        .map_err(|err| err.to_string())?;
+
+    for log in &execution.logs {
+        eprintln!("log: {}", log);
+    }
+    for error in &execution.errors {
+        eprintln!("error: {}", error);
+    }
+    if options.report {
+        eprintln!("{}", execution.report);
+    }
+    match execution.trap {
+        Some(trap) => {
+            eprintln!("trap: {}", trap);
+            Ok(false)
+        }
+        None => {
+            println!("{}", String::from_utf8_lossy(&execution.ret));
+            Ok(true)
+        }
+    }
+}
+
+fn main() {
+    let result = parse(env::args().skip(1).collect()).and_then(run);
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Cost of a contract call, measured while it runs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Instructions, host calls and bytes used by a single call.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Instructions executed, as counted by the wasmi fuel meter
+    pub instructions: u64,
+    /// Number of calls of each host function, by name
+    pub host_calls: BTreeMap<&'static str, u64>,
+    /// Bytes of keys and values written with `_store`
+    pub stored_bytes: u64,
+    /// Bytes of values read with `_get` and `_get_range`
+    pub loaded_bytes: u64,
+    /// Bytes of queries passed to `_kq_query_run` and `_kq_query_page_run`
+    pub query_bytes: u64,
+    /// Bytes of the query results returned to the contract
+    pub result_bytes: u64,
+    /// Bytes the host read from the contract memory
+    pub memory_read: u64,
+    /// Bytes the host wrote to the contract memory
+    pub memory_written: u64,
+    /// True if the call was aborted because it reached the fuel limit
+    pub out_of_fuel: bool,
+}
+
+impl Report {
+    /// Returns how many times the contract called a host function
+    pub fn calls(&self, name: &str) -> u64 {
+        self.host_calls.get(name).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of calls of all host functions
+    pub fn total_calls(&self) -> u64 {
+        self.host_calls.values().sum()
+    }
+
+    pub(crate) fn host_call(&mut self, name: &'static str) {
+        *self.host_calls.entry(name).or_insert(0) += 1;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "instructions:   {}", self.instructions)?;
+        writeln!(f, "host calls:     {}", self.total_calls())?;
+        for (name, count) in &self.host_calls {
+            writeln!(f, "  {:<24}{}", name, count)?;
+        }
+        writeln!(f, "stored bytes:   {}", self.stored_bytes)?;
+        writeln!(f, "loaded bytes:   {}", self.loaded_bytes)?;
+        writeln!(f, "query bytes:    {}", self.query_bytes)?;
+        writeln!(f, "result bytes:   {}", self.result_bytes)?;
+        writeln!(f, "memory read:    {}", self.memory_read)?;
+        write!(f, "memory written: {}", self.memory_written)?;
+        if self.out_of_fuel {
+            write!(f, "\nout of fuel")?;
+        }
+        Ok(())
+    }
+}
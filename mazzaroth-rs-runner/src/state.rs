@@ -0,0 +1,109 @@
+//! In-memory state read and written by the host functions.
+
+use super::report::Report;
+use mazzaroth_rs::external::sql::MemoryEngine;
+use mazzaroth_rs::PublicKey;
+use mazzaroth_xdr::{Argument, Call};
+use xdr_rs_serialize::ser::XDROut;
+
+use std::collections::HashMap;
+
+/// Contract state kept by a `Runner` between calls.
+///
+/// A call that traps leaves the state unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct State {
+    /// Values written with `_store`, by key
+    pub values: HashMap<Vec<u8>, Vec<u8>>,
+    /// Names returned by `_get_account_name`, by account
+    pub accounts: HashMap<PublicKey, String>,
+    /// Account for which `_is_owner` is true
+    pub owner: Option<PublicKey>,
+    /// Tables read and written by the kvquery functions
+    pub tables: MemoryEngine,
+}
+
+/// Input and context of a single contract call.
+#[derive(Clone, Debug, Default)]
+pub struct Transaction {
+    /// Bytes returned by `_fetch_input`, an XDR encoded `Call` for generated contracts
+    pub input: Vec<u8>,
+    /// Key returned by `_fetch_sender`
+    pub sender: PublicKey,
+    /// Hash returned by `_fetch_transaction_hash`
+    pub hash: [u8; 32],
+    /// Id returned by `_fetch_channel_id`
+    pub channel_id: [u8; 32],
+    /// Height returned by `_block_height`
+    pub block_height: u64,
+    /// Timestamp returned by `_block_timestamp`
+    pub block_timestamp: u64,
+}
+
+impl Transaction {
+    /// Transaction with raw input bytes
+    pub fn new(input: Vec<u8>) -> Self {
+        Transaction {
+            input,
+            ..Transaction::default()
+        }
+    }
+
+    /// Transaction calling a function of a contract generated by `mazzaroth_abi`
+    pub fn call(function: &str, arguments: Vec<Argument>) -> Self {
+        let call = Call {
+            function: function.to_string(),
+            arguments,
+        };
+        let mut input = Vec::new();
+        call.write_xdr(&mut input).unwrap();
+        Transaction::new(input)
+    }
+
+    /// Set the sender of the transaction
+    pub fn sender(mut self, sender: PublicKey) -> Self {
+        self.sender = sender;
+        self
+    }
+
+    /// Set the hash of the transaction
+    pub fn hash(mut self, hash: [u8; 32]) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Set the channel the transaction is executed on
+    pub fn channel_id(mut self, channel_id: [u8; 32]) -> Self {
+        self.channel_id = channel_id;
+        self
+    }
+
+    /// Set the height and timestamp of the block including the transaction
+    pub fn block(mut self, height: u64, timestamp: u64) -> Self {
+        self.block_height = height;
+        self.block_timestamp = timestamp;
+        self
+    }
+}
+
+/// Everything a contract call passed back to the host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Execution {
+    /// Bytes passed to `_ret`, empty if the contract returned nothing
+    pub ret: Vec<u8>,
+    /// Messages passed to `_log`, in order
+    pub logs: Vec<String>,
+    /// Messages passed to `_log_error`, in order
+    pub errors: Vec<String>,
+    /// Why execution was aborted, `None` if `main` returned
+    pub trap: Option<String>,
+    /// What the call cost
+    pub report: Report,
+}
+
+impl Execution {
+    /// Returns true if `main` returned without trapping
+    pub fn is_ok(&self) -> bool {
+        self.trap.is_none()
+    }
+}
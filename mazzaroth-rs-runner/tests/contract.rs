@@ -0,0 +1,119 @@
+//! Runs the counter fixture contract built with `mazzaroth-rs` for wasm32.
+//!
+//! The fixture is built with cargo the first time it is needed, so the
+//! `wasm32-unknown-unknown` target must be installed.
+
+use mazzaroth_rs::testing::argument;
+use mazzaroth_rs::PublicKey;
+use mazzaroth_rs_runner::{Runner, Transaction};
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+static BUILD: Once = Once::new();
+
+// Build the fixture contract for wasm32 and returns the path of the artifact
+fn build_fixture() -> PathBuf {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/counter");
+    BUILD.call_once(|| {
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--release", "--target", TARGET, "--manifest-path"])
+            .arg(fixture.join("Cargo.toml"))
+            .status()
+            .expect("could not run cargo");
+        assert!(status.success(), "could not build the fixture contract");
+    });
+    fixture
+        .join("target")
+        .join(TARGET)
+        .join("release/counter.wasm")
+}
+
+fn runner() -> Runner {
+    Runner::from_file(build_fixture()).unwrap()
+}
+
+#[test]
+fn test_counter() {
+    let mut runner = runner();
+
+    let execution = runner
+        .call(Transaction::call("add", vec![argument(2u64)]))
+        .unwrap();
+    assert!(execution.is_ok(), "{:?}", execution);
+    assert_eq!(b"2".to_vec(), execution.ret);
+    assert_eq!(vec!["count 2".to_string()], execution.logs);
+
+    let execution = runner
+        .call(Transaction::call("add", vec![argument(3u64)]))
+        .unwrap();
+    assert_eq!(b"5".to_vec(), execution.ret);
+    assert_eq!(
+        Some(&5u64.to_be_bytes().to_vec()),
+        runner.state().values.get(b"count".as_ref())
+    );
+
+    let execution = runner.call(Transaction::call("count", vec![])).unwrap();
+    assert_eq!(b"5".to_vec(), execution.ret);
+}
+
+#[test]
+fn test_arguments() {
+    let mut runner = runner();
+    let alice = PublicKey::new([1u8; 32]);
+
+    let name = "a name longer than the 16 bytes of a query hash".to_string();
+    let execution = runner
+        .call(Transaction::call("greet", vec![argument(name.clone())]))
+        .unwrap();
+    assert_eq!(format!("hello {}", name).into_bytes(), execution.ret);
+
+    let execution = runner
+        .call(Transaction::call("sender", vec![]).sender(alice))
+        .unwrap();
+    assert_eq!(format!(r#""{}""#, alice).into_bytes(), execution.ret);
+}
+
+#[test]
+fn test_migrate_owner() {
+    let mut runner = runner();
+    let owner = PublicKey::new([1u8; 32]);
+    runner.state_mut().owner = Some(owner);
+    runner
+        .call(Transaction::call("add", vec![argument(4u64)]))
+        .unwrap();
+
+    // the unauthorized error unwrapped by main traps and is logged by the panic hook
+    let execution = runner
+        .call(Transaction::call("reset", vec![]).sender(PublicKey::new([2u8; 32])))
+        .unwrap();
+    assert!(!execution.is_ok());
+    assert!(execution.errors[0].contains("Unauthorized"));
+
+    let execution = runner
+        .call(Transaction::call("reset", vec![]).sender(owner))
+        .unwrap();
+    assert!(execution.is_ok());
+    assert_eq!(b"4".to_vec(), execution.ret);
+}
+
+#[test]
+fn test_panic() {
+    let mut runner = runner();
+    runner
+        .call(Transaction::call("add", vec![argument(u64::MAX)]))
+        .unwrap();
+
+    let execution = runner
+        .call(Transaction::call("add", vec![argument(1u64)]))
+        .unwrap();
+    assert!(execution.trap.is_some());
+    assert!(execution.errors[0].contains("count overflow"));
+    assert_eq!(
+        Some(&u64::MAX.to_be_bytes().to_vec()),
+        runner.state().values.get(b"count".as_ref())
+    );
+}
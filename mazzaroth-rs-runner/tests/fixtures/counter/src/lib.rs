@@ -0,0 +1,88 @@
+//! Counter contract built for wasm32 by the runner integration tests.
+//!
+//! It goes through the wasm side of the host ABI of `mazzaroth-rs`: reading
+//! the input and string arguments, reading and storing values, the sender,
+//! logs and the panic hook.
+
+extern crate mazzaroth_rs;
+extern crate mazzaroth_rs_derive;
+extern crate mazzaroth_xdr;
+
+use mazzaroth_rs::external::{log, persistence, transaction};
+use mazzaroth_rs::{ContractInterface, PublicKey};
+use mazzaroth_rs_derive::mazzaroth_abi;
+
+#[no_mangle]
+pub fn main() {
+    std::panic::set_hook(Box::new(mazzaroth_rs::external::errors::hook));
+
+    let mut contract = Counter::new(Count {});
+    let args = transaction::arguments();
+    let response = contract.execute(&args).unwrap();
+    transaction::ret(response);
+}
+
+#[mazzaroth_abi(Counter)]
+pub trait CounterContract {
+    fn add(&mut self, amount: u64) -> u64;
+
+    #[readonly]
+    fn count(&mut self) -> u64;
+
+    #[readonly]
+    fn greet(&mut self, name: String) -> String;
+
+    #[readonly]
+    fn sender(&mut self) -> PublicKey;
+
+    #[migrate]
+    fn reset(&mut self) -> u64;
+}
+
+pub struct Count {}
+
+const COUNT_KEY: &[u8] = b"count";
+
+fn stored_count() -> u64 {
+    match persistence::get(COUNT_KEY.to_vec()) {
+        Ok(bytes) => {
+            let mut count = [0u8; 8];
+            count.copy_from_slice(&bytes);
+            u64::from_be_bytes(count)
+        }
+        Err(_) => 0,
+    }
+}
+
+fn store_count(count: u64) {
+    persistence::store(COUNT_KEY.to_vec(), count.to_be_bytes().to_vec()).unwrap();
+}
+
+impl CounterContract for Count {
+    fn add(&mut self, amount: u64) -> u64 {
+        let count = stored_count()
+            .checked_add(amount)
+            .expect("count overflow");
+        store_count(count);
+        log(format!("count {}", count));
+        count
+    }
+
+    fn count(&mut self) -> u64 {
+        stored_count()
+    }
+
+    fn greet(&mut self, name: String) -> String {
+        format!("hello {}", name)
+    }
+
+    fn sender(&mut self) -> PublicKey {
+        transaction::sender()
+    }
+
+    fn reset(&mut self) -> u64 {
+        let count = stored_count();
+        store_count(0);
+        count
+    }
+}
@@ -5,16 +5,11 @@ use xdr_rs_serialize::ser::XDROut;
 /// Encoder for returning a number of arguments.
 /// To push a value to the encoder it must implement the Serialize trait for
 /// encoding.
+#[derive(Default)]
 pub struct Encoder {
     values: Vec<u8>,
 }
 
-impl Default for Encoder {
-    fn default() -> Self {
-        Encoder { values: Vec::new() }
-    }
-}
-
 impl Encoder {
     /// Consume `val` to the Encoder
     pub fn push<T: XDROut>(&mut self, val: T, typ: &'static str) {
@@ -0,0 +1,388 @@
+//! Role based access control for contract functions.
+//!
+//! Role memberships are stored in contract state. Every role has an admin
+//! role whose members can grant and revoke it, `ADMIN_ROLE` by default. The
+//! channel owner always holds `ADMIN_ROLE`, so the hierarchy of admin roles is
+//! rooted at the owner.
+//!
+//! Role names must be non-empty and may not contain `/`, which separates the
+//! role from the account in the state key of a membership.
+//!
+//! Every change is reported as a `RoleEvent` written to the host log.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::access;
+//!
+//! const MINTER: &str = "minter";
+//!
+//! impl TokenContract for Token {
+//!     fn add_minter(&mut self, account: PublicKey) {
+//!         access::grant_role(MINTER, account).unwrap();
+//!     }
+//!
+//!     fn mint(&mut self, amount: u64) {
+//!         access::require_role(MINTER);
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use super::crypto::PublicKey;
+use super::external::{account, log, persistence, transaction, ExternalError};
+
+use std::fmt;
+
+/// Role whose members administer every role without an explicit admin role.
+/// The channel owner always holds this role.
+pub const ADMIN_ROLE: &str = "admin";
+
+const MEMBER_PREFIX: &[u8] = b"__mazzaroth_access/member/";
+const ADMIN_PREFIX: &[u8] = b"__mazzaroth_access/admin/";
+
+/// Defines the errors that can be returned when changing or checking roles.
+#[derive(Debug, PartialEq)]
+pub enum AccessError {
+    /// The account does not hold the role required for the operation
+    MissingRole { role: String, account: PublicKey },
+    /// The role name is empty or contains `/`
+    InvalidRole(String),
+    /// Reading or writing role memberships in state failed
+    External(ExternalError),
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessError::MissingRole { role, account } => {
+                write!(f, "Account {} is missing role {}.", account, role)
+            }
+            AccessError::InvalidRole(role) => write!(f, "Invalid role name: {:?}", role),
+            AccessError::External(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ExternalError> for AccessError {
+    fn from(err: ExternalError) -> Self {
+        AccessError::External(err)
+    }
+}
+
+/// A change of role memberships or admin roles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoleEvent {
+    /// `sender` granted `role` to `account`
+    RoleGranted {
+        role: String,
+        account: PublicKey,
+        sender: PublicKey,
+    },
+    /// `sender` revoked `role` from `account`
+    RoleRevoked {
+        role: String,
+        account: PublicKey,
+        sender: PublicKey,
+    },
+    /// The admin role of `role` changed from `previous` to `admin`
+    RoleAdminChanged {
+        role: String,
+        previous: String,
+        admin: String,
+    },
+}
+
+impl RoleEvent {
+    /// JSON object describing the event, as written to the log
+    pub fn to_json(&self) -> String {
+        let event = match self {
+            RoleEvent::RoleGranted {
+                role,
+                account,
+                sender,
+            } => object! {
+                "event" => "RoleGranted",
+                "role" => role.as_str(),
+                "account" => account.to_hex(),
+                "sender" => sender.to_hex()
+            },
+            RoleEvent::RoleRevoked {
+                role,
+                account,
+                sender,
+            } => object! {
+                "event" => "RoleRevoked",
+                "role" => role.as_str(),
+                "account" => account.to_hex(),
+                "sender" => sender.to_hex()
+            },
+            RoleEvent::RoleAdminChanged {
+                role,
+                previous,
+                admin,
+            } => object! {
+                "event" => "RoleAdminChanged",
+                "role" => role.as_str(),
+                "previous" => previous.as_str(),
+                "admin" => admin.as_str()
+            },
+        };
+        event.dump()
+    }
+
+    fn emit(&self) {
+        log(self.to_json());
+    }
+}
+
+fn validate_role(role: &str) -> Result<(), AccessError> {
+    if role.is_empty() || role.contains('/') {
+        return Err(AccessError::InvalidRole(role.to_string()));
+    }
+    Ok(())
+}
+
+fn member_key(role: &str, account: PublicKey) -> Vec<u8> {
+    let mut key = MEMBER_PREFIX.to_vec();
+    key.extend_from_slice(role.as_bytes());
+    key.push(b'/');
+    key.extend_from_slice(account.as_bytes());
+    key
+}
+
+fn admin_key(role: &str) -> Vec<u8> {
+    let mut key = ADMIN_PREFIX.to_vec();
+    key.extend_from_slice(role.as_bytes());
+    key
+}
+
+// Explicit membership stored in state, ignores the owner's implicit admin role
+fn is_member(role: &str, account: PublicKey) -> bool {
+    persistence::get_length(member_key(role, account)).is_ok()
+}
+
+/// Returns true if the account holds the role.
+///
+/// The channel owner always holds `ADMIN_ROLE`.
+pub fn has_role(role: &str, account: PublicKey) -> bool {
+    (role == ADMIN_ROLE && account::is_owner(account)) || is_member(role, account)
+}
+
+/// Returns the admin role whose members can grant and revoke the role.
+///
+/// Roles without an explicit admin role are administered by `ADMIN_ROLE`.
+pub fn role_admin(role: &str) -> Result<String, AccessError> {
+    match persistence::get(admin_key(role)) {
+        Ok(admin) => Ok(String::from_utf8(admin).unwrap_or_else(|_| ADMIN_ROLE.to_string())),
+        Err(ExternalError::MissingKeyError) => Ok(ADMIN_ROLE.to_string()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Check that the account holds the role.
+pub fn check_role(role: &str, account: PublicKey) -> Result<(), AccessError> {
+    if has_role(role, account) {
+        Ok(())
+    } else {
+        Err(AccessError::MissingRole {
+            role: role.to_string(),
+            account,
+        })
+    }
+}
+
+/// Check that the transaction sender holds the role.
+pub fn ensure_role(role: &str) -> Result<(), AccessError> {
+    check_role(role, transaction::sender())
+}
+
+/// Abort the transaction unless the sender holds the role.
+///
+/// Panics with the `AccessError` message, which the panic hook reports to the host.
+pub fn require_role(role: &str) {
+    if let Err(err) = ensure_role(role) {
+        panic!("{}", err);
+    }
+}
+
+/// Abort the transaction unless the sender is the channel owner.
+pub fn require_owner() {
+    let sender = transaction::sender();
+    if !account::is_owner(sender) {
+        panic!("Account {} is not the channel owner.", sender);
+    }
+}
+
+/// Grant a role to an account.
+///
+/// The sender must hold the admin role of the role. Granting a role the
+/// account already holds does nothing.
+pub fn grant_role(role: &str, account: PublicKey) -> Result<(), AccessError> {
+    validate_role(role)?;
+    ensure_role(&role_admin(role)?)?;
+    if is_member(role, account) {
+        return Ok(());
+    }
+    persistence::store(member_key(role, account), vec![1])?;
+    RoleEvent::RoleGranted {
+        role: role.to_string(),
+        account,
+        sender: transaction::sender(),
+    }
+    .emit();
+    Ok(())
+}
+
+/// Revoke a role from an account.
+///
+/// The sender must hold the admin role of the role. The owner's implicit
+/// `ADMIN_ROLE` can not be revoked.
+pub fn revoke_role(role: &str, account: PublicKey) -> Result<(), AccessError> {
+    validate_role(role)?;
+    ensure_role(&role_admin(role)?)?;
+    remove_role(role, account)
+}
+
+/// Give up a role held by the sender.
+pub fn renounce_role(role: &str) -> Result<(), AccessError> {
+    validate_role(role)?;
+    remove_role(role, transaction::sender())
+}
+
+fn remove_role(role: &str, account: PublicKey) -> Result<(), AccessError> {
+    if !is_member(role, account) {
+        return Ok(());
+    }
+    persistence::delete(member_key(role, account))?;
+    RoleEvent::RoleRevoked {
+        role: role.to_string(),
+        account,
+        sender: transaction::sender(),
+    }
+    .emit();
+    Ok(())
+}
+
+/// Change the admin role of a role.
+///
+/// The sender must hold the current admin role of the role.
+pub fn set_role_admin(role: &str, admin: &str) -> Result<(), AccessError> {
+    validate_role(role)?;
+    validate_role(admin)?;
+    let previous = role_admin(role)?;
+    ensure_role(&previous)?;
+    persistence::store(admin_key(role), admin.as_bytes().to_vec())?;
+    RoleEvent::RoleAdminChanged {
+        role: role.to_string(),
+        previous,
+        admin: admin.to_string(),
+    }
+    .emit();
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+    use external::mock;
+
+    fn key(byte: u8) -> PublicKey {
+        PublicKey::new([byte; 32])
+    }
+
+    fn as_sender(sender: PublicKey, owner: bool) {
+        mock::with(|mock| {
+            mock.sender = sender;
+            mock.owner = owner;
+        });
+    }
+
+    #[test]
+    fn test_roles() {
+        let (owner, alice, bob) = (key(40), key(41), key(42));
+
+        as_sender(owner, true);
+        assert!(has_role(ADMIN_ROLE, owner));
+        assert_eq!(Ok(()), grant_role("minter", alice));
+        assert!(has_role("minter", alice));
+        assert!(!has_role("minter", bob));
+
+        // alice is not an admin of minter
+        as_sender(alice, false);
+        assert_eq!(
+            Err(AccessError::MissingRole {
+                role: ADMIN_ROLE.to_string(),
+                account: alice
+            }),
+            grant_role("minter", bob)
+        );
+        assert_eq!(Ok(()), ensure_role("minter"));
+        assert!(ensure_role(ADMIN_ROLE).is_err());
+
+        as_sender(owner, true);
+        assert_eq!(Ok(()), revoke_role("minter", alice));
+        assert!(!has_role("minter", alice));
+
+        as_sender(owner, false);
+        assert!(!has_role(ADMIN_ROLE, owner));
+    }
+
+    #[test]
+    fn test_role_admin() {
+        let (owner, alice, bob) = (key(50), key(51), key(52));
+
+        as_sender(owner, true);
+        assert_eq!(Ok(ADMIN_ROLE.to_string()), role_admin("burner"));
+        assert_eq!(Ok(()), set_role_admin("burner", "burner_admin"));
+        assert_eq!(Ok("burner_admin".to_string()), role_admin("burner"));
+        assert_eq!(Ok(()), grant_role("burner_admin", alice));
+
+        as_sender(alice, false);
+        assert_eq!(Ok(()), grant_role("burner", bob));
+
+        as_sender(bob, false);
+        assert!(set_role_admin("burner", "burner").is_err());
+        assert_eq!(Ok(()), renounce_role("burner"));
+        assert!(!has_role("burner", bob));
+    }
+
+    #[test]
+    fn test_invalid_role() {
+        let (owner, alice) = (key(60), key(61));
+
+        as_sender(owner, true);
+        let invalid = |role: &str| Err(AccessError::InvalidRole(role.to_string()));
+        assert_eq!(invalid(""), grant_role("", alice));
+        assert_eq!(invalid("a/b"), grant_role("a/b", alice));
+        assert_eq!(invalid("a/b"), revoke_role("a/b", alice));
+        assert_eq!(invalid("a/b"), set_role_admin("a/b", "minter"));
+        assert_eq!(invalid(""), set_role_admin("minter", ""));
+        assert!(!has_role("a/b", alice));
+    }
+
+    #[test]
+    fn test_role_admin_error() {
+        as_sender(key(70), true);
+        persistence::set_max_value_size(1);
+        assert_eq!(Ok(()), set_role_admin("pauser", "pauser_admin"));
+        assert_eq!(
+            Err(AccessError::External(ExternalError::ValueSizeError)),
+            role_admin("pauser")
+        );
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = RoleEvent::RoleGranted {
+            role: "minter".to_string(),
+            account: key(1),
+            sender: key(2),
+        };
+        let json = json::parse(&event.to_json()).unwrap();
+        assert_eq!("RoleGranted", json["event"]);
+        assert_eq!("minter", json["role"]);
+        assert_eq!(key(1).to_hex(), json["account"]);
+    }
+}
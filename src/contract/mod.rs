@@ -13,6 +13,7 @@ pub enum ContractErrorKinds {
     DeserializeError(Error),
     InvalidArguments,
     InvalidFunctionName,
+    Unauthorized,
 }
 
 #[derive(Debug)]
@@ -40,6 +41,12 @@ impl ContractError {
             kind: ContractErrorKinds::InvalidFunctionName,
         }
     }
+
+    pub fn unauthorized() -> Self {
+        ContractError {
+            kind: ContractErrorKinds::Unauthorized,
+        }
+    }
 }
 
 impl std::fmt::Display for ContractError {
@@ -54,6 +61,9 @@ impl std::fmt::Display for ContractError {
             ContractErrorKinds::InvalidFunctionName => {
                 write!(f, "Could not find function with given name.")
             }
+            ContractErrorKinds::Unauthorized => {
+                write!(f, "Sender is not authorized to call this function.")
+            }
         }
     }
 }
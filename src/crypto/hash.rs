@@ -0,0 +1,145 @@
+//! Hash functions with a common `Hasher` interface.
+
+extern crate blake2;
+extern crate sha2;
+extern crate sha3;
+
+#[cfg(all(feature = "host-crypto", not(feature = "host-mock")))]
+use external::externs::{_blake2b, _keccak256, _sha256, _sha3_256};
+
+/// A hash function producing a fixed length digest.
+pub trait Hasher {
+    /// Length of the digest in bytes
+    const DIGEST_LENGTH: usize;
+
+    /// Returns the digest of data
+    fn hash(data: &[u8]) -> Vec<u8>;
+
+    /// Returns the digest of data computed in rust, even when the host
+    /// implementation is available
+    fn hash_native(data: &[u8]) -> Vec<u8>;
+}
+
+// Call a host hash function writing DIGEST_LENGTH bytes into the result
+#[cfg(all(feature = "host-crypto", not(feature = "host-mock")))]
+fn host_hash<H: Hasher>(
+    data: &[u8],
+    f: unsafe extern "C" fn(*const u8, usize, *mut u8),
+) -> Vec<u8> {
+    let mut digest = vec![0u8; H::DIGEST_LENGTH];
+    unsafe { f(data.as_ptr(), data.len(), digest.as_mut_ptr()) };
+    digest
+}
+
+macro_rules! hasher {
+    ($(#[$doc:meta])* $name:ident, $native:ty, $length:expr, $host:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq)]
+        pub struct $name;
+
+        impl Hasher for $name {
+            const DIGEST_LENGTH: usize = $length;
+
+            #[cfg(all(feature = "host-crypto", not(feature = "host-mock")))]
+            fn hash(data: &[u8]) -> Vec<u8> {
+                host_hash::<Self>(data, $host)
+            }
+
+            #[cfg(not(all(feature = "host-crypto", not(feature = "host-mock"))))]
+            fn hash(data: &[u8]) -> Vec<u8> {
+                Self::hash_native(data)
+            }
+
+            fn hash_native(data: &[u8]) -> Vec<u8> {
+                use self::sha3::Digest;
+                <$native>::digest(data).to_vec()
+            }
+        }
+    };
+}
+
+hasher!(
+    /// SHA3-256 as standardized in FIPS 202
+    Sha3_256,
+    sha3::Sha3_256,
+    32,
+    _sha3_256
+);
+hasher!(
+    /// Keccak-256 with the original padding, as used by Ethereum
+    Keccak256,
+    sha3::Keccak256,
+    32,
+    _keccak256
+);
+hasher!(
+    /// SHA-256 from the SHA-2 family
+    Sha256,
+    sha2::Sha256,
+    32,
+    _sha256
+);
+hasher!(
+    /// BLAKE2b with a 64 byte digest
+    Blake2b,
+    blake2::Blake2b,
+    64,
+    _blake2b
+);
+
+/// Returns the SHA3-256 digest of data
+pub fn sha3_256(data: &[u8]) -> Vec<u8> {
+    Sha3_256::hash(data)
+}
+
+/// Returns the Keccak-256 digest of data
+pub fn keccak256(data: &[u8]) -> Vec<u8> {
+    Keccak256::hash(data)
+}
+
+/// Returns the SHA-256 digest of data
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::hash(data)
+}
+
+/// Returns the BLAKE2b digest of data
+pub fn blake2b(data: &[u8]) -> Vec<u8> {
+    Blake2b::hash(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digest: Vec<u8>) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_empty_digests() {
+        assert_eq!(
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            hex(sha3_256(b""))
+        );
+        assert_eq!(
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            hex(keccak256(b""))
+        );
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            hex(sha256(b""))
+        );
+        assert_eq!(
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+            hex(blake2b(b""))
+        );
+    }
+
+    #[test]
+    fn test_digest_length() {
+        assert_eq!(Sha3_256::DIGEST_LENGTH, sha3_256(b"abc").len());
+        assert_eq!(Keccak256::DIGEST_LENGTH, keccak256(b"abc").len());
+        assert_eq!(Sha256::DIGEST_LENGTH, sha256(b"abc").len());
+        assert_eq!(Blake2b::DIGEST_LENGTH, blake2b(b"abc").len());
+    }
+}
@@ -0,0 +1,228 @@
+//! Merkle trees and inclusion proofs over SHA3-256.
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes so a proof for an
+//! inner node can not be passed off as a proof for a leaf:
+//!
+//! ```text
+//! leaf = sha3_256(0x00 || data)
+//! node = sha3_256(0x01 || left || right)
+//! ```
+//!
+//! A node without a sibling on its level is moved up to the next level
+//! unchanged. Proofs are XDR encodable so they can be passed as contract
+//! arguments.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::crypto::merkle::{self, MerkleProof, MerkleTree};
+//!
+//! // Off chain, build the tree of the allowlist and store its root in the contract.
+//! let tree = MerkleTree::new(&allowlist);
+//! let proof = tree.proof(3).unwrap();
+//!
+//! // In the contract, check the proof passed as an argument against the stored root.
+//! if !merkle::verify(&root, &account, &proof) {
+//!     return Err(ContractError::unauthorized());
+//! }
+//! ```
+
+use super::sha3_256;
+
+use json;
+use std::io::Write;
+use xdr_rs_serialize::de::{
+    read_var_array, read_var_array_json, read_var_opaque, read_var_opaque_json, XDRIn,
+};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{
+    write_var_array, write_var_array_json, write_var_opaque, write_var_opaque_json, XDROut,
+};
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of a leaf holding data
+pub fn leaf_hash(data: &[u8]) -> Vec<u8> {
+    let mut input = Vec::with_capacity(data.len() + 1);
+    input.push(LEAF_PREFIX);
+    input.extend_from_slice(data);
+    sha3_256(&input)
+}
+
+/// Hash of an inner node with the given children hashes
+pub fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut input = Vec::with_capacity(left.len() + right.len() + 1);
+    input.push(NODE_PREFIX);
+    input.extend_from_slice(left);
+    input.extend_from_slice(right);
+    sha3_256(&input)
+}
+
+/// A sibling hash on the path from a leaf to the root.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct ProofNode {
+    /// Hash of the sibling
+    #[array(var = 32)]
+    pub hash: Vec<u8>,
+    /// True if the sibling is the left child of the parent
+    pub left: bool,
+}
+
+/// Proof that a leaf is included in a tree with a given root.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct MerkleProof {
+    /// Sibling hashes from the leaf level up to the root
+    #[array(var = 2147483647)]
+    pub path: Vec<ProofNode>,
+}
+
+impl MerkleProof {
+    /// Root of the tree the proof leads to when starting from a leaf holding data
+    pub fn root(&self, data: &[u8]) -> Vec<u8> {
+        self.path.iter().fold(leaf_hash(data), |hash, node| {
+            if node.left {
+                node_hash(&node.hash, &hash)
+            } else {
+                node_hash(&hash, &node.hash)
+            }
+        })
+    }
+}
+
+/// Returns true if proof shows that a leaf holding data is part of the tree with root
+pub fn verify(root: &[u8], data: &[u8], proof: &MerkleProof) -> bool {
+    proof.root(data) == root
+}
+
+/// A Merkle tree holding every level of hashes, from the leaves up to the root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleTree {
+    /// Build the tree of a list of leaves
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        let mut levels = vec![leaves
+            .iter()
+            .map(|leaf| leaf_hash(leaf.as_ref()))
+            .collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    /// Number of leaves in the tree
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// True if the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Root hash of the tree, the SHA3-256 hash of nothing for an empty tree
+    pub fn root(&self) -> Vec<u8> {
+        match self.levels[self.levels.len() - 1].first() {
+            Some(root) => root.clone(),
+            None => sha3_256(&[]),
+        }
+    }
+
+    /// Inclusion proof of the leaf at index, `None` if the index is out of range
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut index = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < level.len() {
+                path.push(ProofNode {
+                    hash: level[sibling].clone(),
+                    left: sibling < index,
+                });
+            }
+            index /= 2;
+        }
+        Some(MerkleProof { path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: usize) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i| format!("leaf {}", i).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_proofs() {
+        for count in 1..10 {
+            let leaves = leaves(count);
+            let tree = MerkleTree::new(&leaves);
+            let root = tree.root();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(verify(&root, leaf, &proof));
+                assert!(!verify(&root, b"other", &proof));
+            }
+            assert_eq!(None, tree.proof(count));
+        }
+    }
+
+    #[test]
+    fn test_root() {
+        let tree = MerkleTree::new(&[b"a", b"b", b"c"]);
+        let expected = node_hash(
+            &node_hash(&leaf_hash(b"a"), &leaf_hash(b"b")),
+            &leaf_hash(b"c"),
+        );
+        assert_eq!(expected, tree.root());
+        assert_eq!(leaf_hash(b"a"), MerkleTree::new(&[b"a"]).root());
+
+        let empty: Vec<Vec<u8>> = Vec::new();
+        assert!(MerkleTree::new(&empty).is_empty());
+        assert_eq!(sha3_256(&[]), MerkleTree::new(&empty).root());
+    }
+
+    #[test]
+    fn test_wrong_position() {
+        let leaves = leaves(4);
+        let tree = MerkleTree::new(&leaves);
+        let mut proof = tree.proof(1).unwrap();
+        proof.path[0].left = !proof.path[0].left;
+        assert!(!verify(&tree.root(), &leaves[1], &proof));
+    }
+
+    #[test]
+    fn test_proof_xdr() {
+        let proof = MerkleTree::new(&leaves(5)).proof(4).unwrap();
+        let mut bytes = Vec::new();
+        proof.write_xdr(&mut bytes).unwrap();
+        assert_eq!(proof, MerkleProof::read_xdr(&bytes).unwrap().0);
+
+        let mut json = Vec::new();
+        proof.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(
+            proof,
+            MerkleProof::read_json(json::parse(&json).unwrap()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Cryptographic functions available to contracts.
+//!
+//! Hash functions are implemented natively in rust so they can be used in
+//! tests and with the `host-mock` feature. Wasm builds with the `host-crypto`
+//! feature call the host implementations instead, which are faster than
+//! running the native code in the VM.
+//!
+//! Ed25519 keys are generated, and messages signed and verified, by the host.
+//! With the `host-mock` feature they are implemented natively instead, with
+//! deterministic key generation.
+//!
+//! The `merkle` module builds Merkle trees and checks inclusion proofs.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::crypto::{self, Hasher, Keccak256};
+//!
+//! let digest = crypto::sha3_256(b"hello");
+//! let digest = Keccak256::hash(b"hello");
+//! ```
+
+mod hash;
+pub use self::hash::{
+    blake2b, keccak256, sha256, sha3_256, Blake2b, Hasher, Keccak256, Sha256, Sha3_256,
+};
+
+pub mod merkle;
+
+mod public_key;
+pub use self::public_key::{Address, ParseKeyError, PublicKey};
+
+mod signature;
+pub use self::signature::{
+    generate_key_pair, sign_message, verify, KeyPair, PRIVATE_KEY_LENGTH, PUBLIC_KEY_LENGTH,
+    SIGNATURE_LENGTH,
+};
@@ -0,0 +1,239 @@
+//! The public key identifying an account.
+
+extern crate base32;
+extern crate bs58;
+extern crate hex;
+
+use super::PUBLIC_KEY_LENGTH;
+use external::ExternalError;
+
+use std::fmt;
+use std::str::FromStr;
+use xdr_rs_serialize::de::{read_fixed_opaque, read_fixed_opaque_json, XDRIn};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{write_fixed_opaque, write_fixed_opaque_json, XDROut};
+
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+/// Defines the errors that can be returned when parsing a public key from a string.
+#[derive(Debug, PartialEq)]
+pub enum ParseKeyError {
+    /// The string is not valid in the expected encoding
+    InvalidEncoding,
+    /// The decoded key is not 32 bytes, holds the decoded length
+    InvalidLength(usize),
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseKeyError::InvalidEncoding => write!(f, "Public key is not correctly encoded."),
+            ParseKeyError::InvalidLength(len) => {
+                write!(f, "Public key must be 32 bytes, found {}.", len)
+            }
+        }
+    }
+}
+
+/// A 32 byte Ed25519 public key identifying an account.
+///
+/// Public keys are XDR encoded as fixed 32 byte opaque values and JSON encoded
+/// as hex strings, and can be used as contract arguments and return values
+/// with the ABI type "address". `Display` and `FromStr` use the hex form.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PublicKey([u8; PUBLIC_KEY_LENGTH]);
+
+/// The address of an account is its public key.
+pub type Address = PublicKey;
+
+impl PublicKey {
+    /// Public key with the given bytes
+    pub fn new(bytes: [u8; PUBLIC_KEY_LENGTH]) -> Self {
+        PublicKey(bytes)
+    }
+
+    /// Public key from a slice, `ExternalError::KeyLengthError` if it is not 32 bytes
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ExternalError> {
+        if bytes.len() != PUBLIC_KEY_LENGTH {
+            return Err(ExternalError::KeyLengthError);
+        }
+        let mut key = [0u8; PUBLIC_KEY_LENGTH];
+        key.copy_from_slice(bytes);
+        Ok(PublicKey(key))
+    }
+
+    /// Bytes of the key
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LENGTH] {
+        &self.0
+    }
+
+    /// Bytes of the key as a vector
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Lowercase hex encoding of the key
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Parse a hex encoded key
+    pub fn from_hex(s: &str) -> Result<Self, ParseKeyError> {
+        let bytes = hex::decode(s).map_err(|_| ParseKeyError::InvalidEncoding)?;
+        PublicKey::parsed(&bytes)
+    }
+
+    /// Unpadded RFC 4648 base32 encoding of the key
+    pub fn to_base32(&self) -> String {
+        base32::encode(BASE32, &self.0)
+    }
+
+    /// Parse an unpadded RFC 4648 base32 encoded key
+    pub fn from_base32(s: &str) -> Result<Self, ParseKeyError> {
+        let bytes = base32::decode(BASE32, s).ok_or(ParseKeyError::InvalidEncoding)?;
+        PublicKey::parsed(&bytes)
+    }
+
+    /// Base58 encoding of the key with the bitcoin alphabet
+    pub fn to_base58(&self) -> String {
+        bs58::encode(self.0).into_string()
+    }
+
+    /// Parse a base58 encoded key with the bitcoin alphabet
+    pub fn from_base58(s: &str) -> Result<Self, ParseKeyError> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| ParseKeyError::InvalidEncoding)?;
+        PublicKey::parsed(&bytes)
+    }
+
+    fn parsed(bytes: &[u8]) -> Result<Self, ParseKeyError> {
+        PublicKey::from_slice(bytes).map_err(|_| ParseKeyError::InvalidLength(bytes.len()))
+    }
+}
+
+impl From<[u8; PUBLIC_KEY_LENGTH]> for PublicKey {
+    fn from(bytes: [u8; PUBLIC_KEY_LENGTH]) -> Self {
+        PublicKey(bytes)
+    }
+}
+
+impl From<PublicKey> for Vec<u8> {
+    fn from(key: PublicKey) -> Self {
+        key.to_vec()
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PublicKey({})", self.to_hex())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PublicKey::from_hex(s)
+    }
+}
+
+impl XDROut for PublicKey {
+    fn write_xdr(&self, out: &mut Vec<u8>) -> Result<u64, Error> {
+        write_fixed_opaque(&self.to_vec(), PUBLIC_KEY_LENGTH as u32, out)
+    }
+
+    fn write_json(&self, out: &mut Vec<u8>) -> Result<u64, Error> {
+        write_fixed_opaque_json(&self.to_vec(), PUBLIC_KEY_LENGTH as u32, out)
+    }
+}
+
+impl XDRIn for PublicKey {
+    fn read_xdr(buffer: &[u8]) -> Result<(Self, u64), Error> {
+        let (bytes, read) = read_fixed_opaque(PUBLIC_KEY_LENGTH as u32, buffer)?;
+        Ok((
+            PublicKey::from_slice(&bytes).map_err(|_| Error::bad_array_size())?,
+            read,
+        ))
+    }
+
+    fn read_json(jval: json::JsonValue) -> Result<Self, Error> {
+        let bytes = read_fixed_opaque_json(PUBLIC_KEY_LENGTH as u32, jval)?;
+        PublicKey::from_slice(&bytes).map_err(|_| Error::bad_array_size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> PublicKey {
+        let mut bytes = [0u8; PUBLIC_KEY_LENGTH];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        PublicKey::new(bytes)
+    }
+
+    #[test]
+    fn test_string_forms() {
+        let key = key();
+        assert_eq!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            key.to_string()
+        );
+        assert_eq!(Ok(key), key.to_string().parse());
+        assert_eq!(Ok(key), PublicKey::from_base32(&key.to_base32()));
+        assert_eq!(Ok(key), PublicKey::from_base58(&key.to_base58()));
+
+        assert_eq!(
+            Err(ParseKeyError::InvalidEncoding),
+            PublicKey::from_hex("zz")
+        );
+        assert_eq!(
+            Err(ParseKeyError::InvalidLength(2)),
+            PublicKey::from_hex("0102")
+        );
+        assert_eq!(
+            Err(ParseKeyError::InvalidEncoding),
+            PublicKey::from_base58("0OIl")
+        );
+    }
+
+    #[test]
+    fn test_from_slice() {
+        assert_eq!(Ok(key()), PublicKey::from_slice(&key().to_vec()));
+        assert_eq!(
+            Err(ExternalError::KeyLengthError),
+            PublicKey::from_slice(&[1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_xdr_json() {
+        let key = key();
+        let mut bytes = Vec::new();
+        assert_eq!(Ok(32), key.write_xdr(&mut bytes));
+        assert_eq!(key.to_vec(), bytes);
+        assert_eq!(Ok((key, 32)), PublicKey::read_xdr(&bytes));
+
+        let mut out = Vec::new();
+        key.write_json(&mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(format!(r#""{}""#, key.to_hex()), json);
+        assert_eq!(Ok(key), PublicKey::read_json(json::parse(&json).unwrap()));
+        assert!(PublicKey::read_json(json::parse(r#""0102""#).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Ed25519 signatures.
+
+#[cfg(feature = "host-mock")]
+extern crate ed25519_dalek;
+
+use super::PublicKey;
+use external::ExternalError;
+
+#[cfg(not(feature = "host-mock"))]
+use external::externs::{_generate_key_pair, _sign_message, _verify_signature};
+
+#[cfg(feature = "host-mock")]
+use std::cell::Cell;
+
+/// Length in bytes of an Ed25519 public key
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Length in bytes of an Ed25519 private key
+pub const PRIVATE_KEY_LENGTH: usize = 32;
+
+/// Length in bytes of an Ed25519 signature
+pub const SIGNATURE_LENGTH: usize = 64;
+
+#[cfg(feature = "host-mock")]
+thread_local! {
+    // Number of key pairs generated by the mock, used to derive the next key
+    static KEY_PAIR_COUNT: Cell<u64> = const { Cell::new(0) };
+}
+
+/// An Ed25519 key pair.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPair {
+    /// 32 byte private key
+    pub private_key: Vec<u8>,
+    /// Public key of the pair
+    pub public_key: PublicKey,
+}
+
+/// Generate a new Ed25519 key pair.
+///
+/// With the `host-mock` feature keys are derived from the number of key pairs
+/// generated so far on the current thread, so every test gets the same
+/// sequence of keys.
+///
+/// # Returns
+///
+///  Result<KeyPair, ExternalError>
+///  * `Ok(KeyPair)` - the generated key pair
+///  * `Err(ExternalError::KeyPairGenerateError)` - the host failed to generate a key pair
+#[cfg(not(feature = "host-mock"))]
+pub fn generate_key_pair() -> Result<KeyPair, ExternalError> {
+    let mut private_key = vec![0u8; PRIVATE_KEY_LENGTH];
+    let mut public_key = [0u8; PUBLIC_KEY_LENGTH];
+    if !unsafe { _generate_key_pair(private_key.as_mut_ptr(), public_key.as_mut_ptr()) } {
+        return Err(ExternalError::KeyPairGenerateError);
+    }
+    Ok(KeyPair {
+        private_key,
+        public_key: PublicKey::new(public_key),
+    })
+}
+
+#[cfg(feature = "host-mock")]
+pub fn generate_key_pair() -> Result<KeyPair, ExternalError> {
+    use self::ed25519_dalek::SigningKey;
+    use crypto::sha3_256;
+
+    let count = KEY_PAIR_COUNT.with(|count| {
+        count.set(count.get() + 1);
+        count.get()
+    });
+    let mut seed = b"mazzaroth-mock-key".to_vec();
+    seed.extend_from_slice(&count.to_be_bytes());
+    let private_key = sha3_256(&seed);
+
+    let mut secret = [0u8; PRIVATE_KEY_LENGTH];
+    secret.copy_from_slice(&private_key);
+    let public_key = PublicKey::new(SigningKey::from_bytes(&secret).verifying_key().to_bytes());
+    Ok(KeyPair {
+        private_key,
+        public_key,
+    })
+}
+
+/// Sign a message with an Ed25519 private key.
+///
+/// # Arguments
+///
+/// * `private_key` - 32 byte private key of the signer
+/// * `message` - The message to sign
+///
+/// # Returns
+///
+///  Result<Vec<u8>, ExternalError>
+///  * `Ok(Vec<u8>)` - 64 byte signature of the message
+///  * `Err(ExternalError::KeyLengthError)` - the private key is not 32 bytes
+///  * `Err(ExternalError::SignMessageError)` - the host failed to sign the message
+pub fn sign_message(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>, ExternalError> {
+    if private_key.len() != PRIVATE_KEY_LENGTH {
+        return Err(ExternalError::KeyLengthError);
+    }
+    sign_impl(private_key, message)
+}
+
+#[cfg(not(feature = "host-mock"))]
+fn sign_impl(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>, ExternalError> {
+    let mut signature = vec![0u8; SIGNATURE_LENGTH];
+    let signed = unsafe {
+        _sign_message(
+            private_key.as_ptr(),
+            private_key.len(),
+            message.as_ptr(),
+            message.len(),
+            signature.as_mut_ptr(),
+        )
+    };
+    if !signed {
+        return Err(ExternalError::SignMessageError);
+    }
+    Ok(signature)
+}
+
+#[cfg(feature = "host-mock")]
+fn sign_impl(private_key: &[u8], message: &[u8]) -> Result<Vec<u8>, ExternalError> {
+    use self::ed25519_dalek::{Signer, SigningKey};
+
+    let mut secret = [0u8; PRIVATE_KEY_LENGTH];
+    secret.copy_from_slice(private_key);
+    Ok(SigningKey::from_bytes(&secret)
+        .sign(message)
+        .to_bytes()
+        .to_vec())
+}
+
+/// Verify an Ed25519 signature of a message.
+///
+/// # Arguments
+///
+/// * `public_key` - 32 byte public key of the signer
+/// * `message` - The signed message
+/// * `signature` - 64 byte signature of the message
+///
+/// # Returns
+///
+///  Result<bool, ExternalError>
+///  * `Ok(true)` - the signature of the message is valid for the public key
+///  * `Ok(false)` - the signature is not valid
+///  * `Err(ExternalError::KeyLengthError)` - the public key is not 32 bytes
+///  * `Err(ExternalError::SignatureLengthError)` - the signature is not 64 bytes
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::crypto;
+/// if !crypto::verify(&signer, &approval, &signature)? {
+///     return Err(ContractError::unauthorized());
+/// }
+/// ```
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool, ExternalError> {
+    if public_key.len() != PUBLIC_KEY_LENGTH {
+        return Err(ExternalError::KeyLengthError);
+    }
+    if signature.len() != SIGNATURE_LENGTH {
+        return Err(ExternalError::SignatureLengthError);
+    }
+    Ok(verify_impl(public_key, message, signature))
+}
+
+#[cfg(not(feature = "host-mock"))]
+fn verify_impl(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    unsafe {
+        _verify_signature(
+            public_key.as_ptr(),
+            public_key.len(),
+            message.as_ptr(),
+            message.len(),
+            signature.as_ptr(),
+            signature.len(),
+        )
+    }
+}
+
+#[cfg(feature = "host-mock")]
+fn verify_impl(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    use self::ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let mut key = [0u8; PUBLIC_KEY_LENGTH];
+    key.copy_from_slice(public_key);
+    let mut sig = [0u8; SIGNATURE_LENGTH];
+    sig.copy_from_slice(signature);
+    match VerifyingKey::from_bytes(&key) {
+        Ok(key) => key.verify(message, &Signature::from_bytes(&sig)).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::ed25519_dalek::{Signer, SigningKey};
+    use super::*;
+
+    fn signed(message: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        (
+            key.verifying_key().to_bytes().to_vec(),
+            key.sign(message).to_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let (public_key, signature) = signed(b"approve");
+        assert_eq!(Ok(true), verify(&public_key, b"approve", &signature));
+        assert_eq!(Ok(false), verify(&public_key, b"reject", &signature));
+
+        let mut tampered = signature.clone();
+        tampered[0] ^= 1;
+        assert_eq!(Ok(false), verify(&public_key, b"approve", &tampered));
+    }
+
+    #[test]
+    fn test_generate_and_sign() {
+        let first = generate_key_pair().unwrap();
+        let second = generate_key_pair().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(PRIVATE_KEY_LENGTH, first.private_key.len());
+        assert_eq!(PUBLIC_KEY_LENGTH, first.public_key.as_bytes().len());
+
+        let signature = sign_message(&first.private_key, b"approve").unwrap();
+        assert_eq!(
+            signature,
+            sign_message(&first.private_key, b"approve").unwrap()
+        );
+        assert_eq!(
+            Ok(true),
+            verify(first.public_key.as_ref(), b"approve", &signature)
+        );
+        assert_eq!(
+            Ok(false),
+            verify(second.public_key.as_ref(), b"approve", &signature)
+        );
+
+        assert_eq!(
+            Err(ExternalError::KeyLengthError),
+            sign_message(&first.private_key[..31], b"approve")
+        );
+    }
+
+    #[test]
+    fn test_verify_lengths() {
+        let (public_key, signature) = signed(b"approve");
+        assert_eq!(
+            Err(ExternalError::KeyLengthError),
+            verify(&public_key[1..], b"approve", &signature)
+        );
+        assert_eq!(
+            Err(ExternalError::SignatureLengthError),
+            verify(&public_key, b"approve", &signature[..63])
+        );
+    }
+}
@@ -1,10 +1,68 @@
 //! Provides access to contract account objects stored in state.
 
+use super::ExternalError;
+use crypto::PublicKey;
+use host::with_host;
+
 #[cfg(not(feature = "host-mock"))]
-use super::externs::_is_owner;
+use super::externs::{_get_account_name, _get_account_name_length, _is_owner};
 
 #[cfg(feature = "host-mock")]
-pub static mut OWNER: bool = false;
+use super::mock;
+
+/// Get the name of an account.
+///
+/// # Arguments
+///
+/// * `key` - The public key of the account
+///
+/// # Returns
+///
+/// Result<String, ExternalError>
+/// * `String` - The name of the account
+/// * `ExternalError` - Error if the account has no name or the name is not valid UTF-8
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::{account, transaction};
+/// let name = account::name(transaction::sender())?;
+/// ```
+pub fn name(key: PublicKey) -> Result<String, ExternalError> {
+    with_host(|host| host.account_name(key))
+}
+
+// Raw host operations used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_name(key: PublicKey) -> Result<String, ExternalError> {
+    let key = key.as_bytes();
+    let len = unsafe { _get_account_name_length(key.as_ptr(), key.len()) };
+    if len == 0 {
+        return Err(ExternalError::MissingAccountNameError);
+    }
+    let mut name = vec![0u8; len as usize];
+    unsafe { _get_account_name(key.as_ptr(), key.len(), name.as_mut_ptr()) };
+    String::from_utf8(name).map_err(|_| ExternalError::AccountNameEncodingError)
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_name(key: PublicKey) -> Result<String, ExternalError> {
+    mock::with(|mock| match mock.accounts.get(&key) {
+        Some(name) if !name.is_empty() => Ok(name.clone()),
+        _ => Err(ExternalError::MissingAccountNameError),
+    })
+}
+
+/// Set the name returned by the mock `name` function for an account.
+///
+/// `None` removes the account name.
+#[cfg(feature = "host-mock")]
+pub fn set_name(key: PublicKey, name: Option<&str>) {
+    mock::with(|mock| match name {
+        Some(name) => mock.accounts.insert(key, name.to_string()),
+        None => mock.accounts.remove(&key),
+    });
+}
 
 /// Check if an account is the owner of the channel.
 ///
@@ -20,28 +78,43 @@ pub static mut OWNER: bool = false;
 ///
 /// ```ignore
 /// use mazzaroth_rs::account;
-/// let is_owner = account::is_owner(vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+/// let is_owner = account::is_owner(transaction::sender());
 /// ```
+pub fn is_owner(key: PublicKey) -> bool {
+    with_host(|host| host.is_owner(key))
+}
+
 #[cfg(not(feature = "host-mock"))]
-pub fn is_owner(key: Vec<u8>) -> bool {
-    unsafe { _is_owner(key.as_ptr(), key.len()) }
+pub(crate) fn raw_is_owner(key: PublicKey) -> bool {
+    unsafe { _is_owner(key.as_bytes().as_ptr(), key.as_bytes().len()) }
 }
 
 #[cfg(feature = "host-mock")]
-pub fn is_owner(_key: Vec<u8>) -> bool {
-    unsafe { OWNER }
+pub(crate) fn raw_is_owner(_key: PublicKey) -> bool {
+    mock::with(|mock| mock.owner)
 }
 
 #[cfg(test)]
 #[cfg(feature = "host-mock")]
 mod tests {
+    use super::super::mock::MockHost;
     use super::*;
 
     #[test]
     fn test_is_owner_true() {
-        unsafe {
-            OWNER = true;
-        }
-        assert_eq!(is_owner(vec![]), true);
+        let _mock = MockHost::new().owner(true).install();
+        assert!(is_owner(PublicKey::default()));
+    }
+
+    #[test]
+    fn test_name() {
+        let alice = PublicKey::new([1u8; 32]);
+        let bob = PublicKey::new([2u8; 32]);
+        set_name(alice, Some("alice"));
+        assert_eq!(Ok("alice".to_string()), name(alice));
+        assert_eq!(Err(ExternalError::MissingAccountNameError), name(bob));
+
+        set_name(alice, None);
+        assert_eq!(Err(ExternalError::MissingAccountNameError), name(alice));
     }
 }
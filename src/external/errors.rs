@@ -18,19 +18,32 @@ use std::panic;
 
 cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
+        #[allow(deprecated)]
         fn hook_impl(info: &panic::PanicInfo) {
             let val = info.to_string().into_bytes();
             unsafe { _log_error(val.as_ptr(), val.len()) };
         }
+    } else if #[cfg(feature = "host-mock")] {
+        use super::mock::{self, HostCall};
+        use std::io::{self, Write};
+
+        #[allow(deprecated)]
+        fn hook_impl(info: &panic::PanicInfo) {
+            let _ = writeln!(io::stderr(), "{}", info);
+            mock::record(HostCall::LogError(info.to_string()));
+        }
     } else {
         use std::io::{self, Write};
 
+        #[allow(deprecated)]
         fn hook_impl(info: &panic::PanicInfo) {
             let _ = writeln!(io::stderr(), "{}", info);
         }
     }
 }
 
+// `PanicInfo` is deprecated in favour of `PanicHookInfo` since rust 1.82 but
+// is the same type, it is kept to support older toolchains.
 /// A panic hook that logs panics to extern _error when building with wasm32
 ///
 /// # How to use
@@ -40,6 +53,7 @@ cfg_if! {
 /// ```ignore
 /// std::panic::set_hook(Box::new(mazzaroth_rs::external::errors::hook));
 /// ```
+#[allow(deprecated)]
 pub fn hook(info: &panic::PanicInfo) {
     hook_impl(info);
 }
@@ -55,6 +69,24 @@ pub enum ExternalError {
     KeyLengthError,
     /// Occurs when the crypto `sign_message` function fails
     SignMessageError,
+    /// Occurs when calling the crypto `verify` function with a signature of the wrong length
+    SignatureLengthError,
+    /// Occurs when calling the account `name` function for an account without a name
+    MissingAccountNameError,
+    /// Occurs when the name returned by the account `name` function is not valid UTF-8
+    AccountNameEncodingError,
+    /// Occurs when a stored value is larger than the configured maximum value size
+    ValueSizeError,
+    /// Occurs when the buffer supplied to `get_into` is too small to hold the value
+    BufferSizeError,
+    /// Occurs when calling `get_range` with a range outside of the stored value
+    RangeError,
+    /// Occurs when calling `store` with a value that exceeds the configured storage quota
+    QuotaExceededError,
+    /// Occurs when calling `store` or `delete` with a key reserved for the storage usage counters
+    ReservedKeyError,
+    /// Occurs when a value stored by this library, such as a usage counter, cannot be decoded
+    DecodeError,
 }
 
 impl std::fmt::Display for ExternalError {
@@ -64,6 +96,15 @@ impl std::fmt::Display for ExternalError {
             ExternalError::KeyPairGenerateError => "Problem generating key pair.",
             ExternalError::KeyLengthError => "Incorrect key length.",
             ExternalError::SignMessageError => "Problem signing message.",
+            ExternalError::SignatureLengthError => "Incorrect signature length.",
+            ExternalError::MissingAccountNameError => "Could not find account name.",
+            ExternalError::AccountNameEncodingError => "Account name is not valid UTF-8.",
+            ExternalError::ValueSizeError => "Value exceeds the maximum value size.",
+            ExternalError::BufferSizeError => "Buffer is too small to hold the value.",
+            ExternalError::RangeError => "Range is outside of the stored value.",
+            ExternalError::QuotaExceededError => "Storage quota exceeded.",
+            ExternalError::ReservedKeyError => "Key is reserved.",
+            ExternalError::DecodeError => "Stored value could not be decoded.",
         };
         write!(f, "{}", message)
     }
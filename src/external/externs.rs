@@ -1,5 +1,3 @@
-pub(crate) const PUBLIC_KEY_LENGTH: usize = 32;
-
 extern "C" {
     /// Fetches input from the Runtime.
     /// Parameter input should be the mut pointer to a vector with length and capacity allocated.
@@ -15,6 +13,19 @@ extern "C" {
     /// Call _input_length first to get a length used to allocate the input vector.
     pub(crate) fn _fetch_sender(sender: *mut u8);
 
+    /// Fetches the 32 byte hash of the transaction being executed.
+    pub(crate) fn _fetch_transaction_hash(hash: *mut u8);
+
+    /// Fetches the 32 byte id of the channel the transaction is executed on.
+    pub(crate) fn _fetch_channel_id(channel_id: *mut u8);
+
+    /// Returns the height of the block that includes the transaction.
+    pub(crate) fn _block_height() -> u64;
+
+    /// Returns the timestamp of the block that includes the transaction in seconds since
+    /// the unix epoch.
+    pub(crate) fn _block_timestamp() -> u64;
+
     /// Returns ptr to bytes to the runtime if a call needs to return a value.
     pub(crate) fn _ret(bytes: *const u8, bytes_length: usize);
 
@@ -33,6 +44,17 @@ extern "C" {
     /// Use the return to set the capacity and length of a vector to call _get.
     pub(crate) fn _get_length(key: *const u8, key_length: usize) -> u32;
 
+    /// Get a byte range of the value for key from the persistent DB provided by the runtime.
+    /// Copies at most value_length bytes starting at offset into value and returns the
+    /// number of bytes copied.
+    pub(crate) fn _get_range(
+        key: *const u8,
+        key_length: usize,
+        offset: u32,
+        value: *mut u8,
+        value_length: usize,
+    ) -> u32;
+
     /// Returns if the key exists in the persistent DB.
     pub(crate) fn _key_exists(key: *const u8, key_length: usize) -> bool;
 
@@ -40,6 +62,11 @@ extern "C" {
     /// Use the return to set the capacity and length of a vector to call _get_account_name.
     pub(crate) fn _get_account_name_length(key: *const u8, key_length: usize) -> u32;
 
+    /// Get the name associated with the account key from the persistent DB.
+    /// Parameter name should be the mut pointer to a vector with length and capacity allocated.
+    /// Call _get_account_name_length first to get a length to allocate the name vector.
+    pub(crate) fn _get_account_name(key: *const u8, key_length: usize, name: *mut u8);
+
     /// Check if a particular account is the owner of the contract.
     pub(crate) fn _is_owner(key: *const u8, key_length: usize) -> bool;
 
@@ -58,10 +85,84 @@ extern "C" {
         json_len: usize,
     ) -> u32;
 
+    /// Updates the rows of a table matching a kvquery filter with the fields of a json
+    /// object. The number of updated rows is written to affected.
+    pub(crate) fn _kq_json_update(
+        table_name_ptr: *const u8,
+        table_name_len: usize,
+        filter_ptr: *const u8,
+        filter_len: usize,
+        json_ptr: *const u8,
+        json_len: usize,
+        affected: *mut u32,
+    ) -> u32;
+
+    /// Deletes the rows of a table matching a kvquery filter. The number of deleted
+    /// rows is written to affected.
+    pub(crate) fn _kq_delete(
+        table_name_ptr: *const u8,
+        table_name_len: usize,
+        filter_ptr: *const u8,
+        filter_len: usize,
+        affected: *mut u32,
+    ) -> u32;
+
     /// Queries and returns its length and a 16 byte hash to look fetch the
     /// result with by running _kq_query_fetch
-    pub(crate) fn _kq_query_run(query: *const u8, query_length: usize, hash: *const u8) -> u32;
+    pub(crate) fn _kq_query_run(query: *const u8, query_length: usize, hash: *mut u8) -> u32;
+
+    /// Queries a page of at most page_size rows starting at the continuation token
+    /// and returns its length and a 16 byte hash to fetch the XDR encoded page with
+    /// by running _kq_query_fetch. An empty token starts at the first row.
+    pub(crate) fn _kq_query_page_run(
+        query: *const u8,
+        query_length: usize,
+        token: *const u8,
+        token_length: usize,
+        page_size: u32,
+        hash: *mut u8,
+    ) -> u32;
 
-    /// Fetches the results of a _kq_query_run execution
-    pub(crate) fn _kq_query_fetch(result: *const u8, hash: *const u8);
+    /// Fetches the results of a _kq_query_run or _kq_query_page_run execution
+    pub(crate) fn _kq_query_fetch(result: *mut u8, hash: *const u8);
+
+    /// Generates an Ed25519 key pair, writing the 32 byte private key and the
+    /// 32 byte public key. Returns false if the key pair could not be generated.
+    pub(crate) fn _generate_key_pair(private_key: *mut u8, public_key: *mut u8) -> bool;
+
+    /// Signs message with the Ed25519 private_key, writing the 64 byte signature.
+    /// Returns false if the message could not be signed.
+    pub(crate) fn _sign_message(
+        private_key: *const u8,
+        private_key_length: usize,
+        message: *const u8,
+        message_length: usize,
+        signature: *mut u8,
+    ) -> bool;
+
+    /// Returns true if signature is a valid Ed25519 signature of message for public_key.
+    pub(crate) fn _verify_signature(
+        public_key: *const u8,
+        public_key_length: usize,
+        message: *const u8,
+        message_length: usize,
+        signature: *const u8,
+        signature_length: usize,
+    ) -> bool;
+
+    /// Writes the 32 byte SHA3-256 digest of data into hash.
+    #[cfg(feature = "host-crypto")]
+    pub(crate) fn _sha3_256(data: *const u8, data_length: usize, hash: *mut u8);
+
+    /// Writes the 32 byte Keccak-256 digest of data into hash.
+    #[cfg(feature = "host-crypto")]
+    pub(crate) fn _keccak256(data: *const u8, data_length: usize, hash: *mut u8);
+
+    /// Writes the 32 byte SHA-256 digest of data into hash.
+    #[cfg(feature = "host-crypto")]
+    pub(crate) fn _sha256(data: *const u8, data_length: usize, hash: *mut u8);
+
+    /// Writes the 64 byte BLAKE2b digest of data into hash.
+    #[cfg(feature = "host-crypto")]
+    pub(crate) fn _blake2b(data: *const u8, data_length: usize, hash: *mut u8);
 }
@@ -1,16 +1,25 @@
 #[cfg(not(feature = "host-mock"))]
 use super::externs::_log;
+#[cfg(feature = "host-mock")]
+use super::mock::{self, HostCall};
+
+use host::with_host;
 
 /// Write a message to the host defined log location.
-#[cfg(not(feature = "host-mock"))]
 pub fn log(msg: String) {
-    let val = msg.into_bytes();
-    unsafe { _log(val.as_ptr(), val.len()) }
+    with_host(|host| host.log(&msg))
+}
+
+// Raw host operation used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_log(msg: &str) {
+    unsafe { _log(msg.as_ptr(), msg.len()) }
 }
 
 #[cfg(feature = "host-mock")]
-pub fn log(msg: String) {
+pub(crate) fn raw_log(msg: &str) {
     println!("log {}", msg);
+    mock::record(HostCall::Log(msg.to_string()));
 }
 
 #[cfg(test)]
@@ -0,0 +1,450 @@
+//! Mock host state read and written by the `external` functions when built
+//! with the `host-mock` feature.
+//!
+//! Every thread has its own `MockHost`, so tests running in parallel never
+//! share state. A test describes the host it needs with the builder methods
+//! and installs it for the rest of the test, or for the duration of a closure.
+//!
+//! Every `ret`, `log`, panic reported by the panic hook, store, delete and
+//! insert reaching the mock host is recorded in order as a `HostCall`, to be
+//! inspected or asserted on after the contract ran.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::external::mock::MockHost;
+//! use mazzaroth_rs::external::{persistence, transaction};
+//!
+//! let _mock = MockHost::new()
+//!     .sender(PublicKey::new([1u8; 32]))
+//!     .owner(true)
+//!     .state(b"count", &[1])
+//!     .install();
+//!
+//! assert_eq!(PublicKey::new([1u8; 32]), transaction::sender());
+//! assert_eq!(Ok(vec![1]), persistence::get(b"count".to_vec()));
+//!
+//! log("counted".to_string());
+//! mock::assert_logged("counted");
+//! ```
+
+use super::persistence::{self, Quota};
+use super::sql::{MemoryEngine, ResultSet};
+use super::transaction::{CHANNEL_ID_LENGTH, HASH_LENGTH};
+use crypto::PublicKey;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+
+thread_local! {
+    static MOCK: RefCell<MockHost> = RefCell::new(MockHost::new());
+}
+
+/// A call made to the mock host, recorded in the order calls are made.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostCall {
+    /// `transaction::ret` returned the bytes
+    Ret(Vec<u8>),
+    /// `log` wrote the message
+    Log(String),
+    /// The panic hook reported the message
+    LogError(String),
+    /// A value was stored in the contract state
+    Store { key: Vec<u8>, value: Vec<u8> },
+    /// A key was deleted from the contract state
+    Delete { key: Vec<u8> },
+    /// `sql::insert` was called with the JSON object, whether or not it succeeded
+    Insert { table: String, json: String },
+}
+
+/// State of the mock host of a thread.
+pub struct MockHost {
+    pub(crate) arguments: Vec<u8>,
+    pub(crate) sender: PublicKey,
+    pub(crate) owner: bool,
+    pub(crate) hash: [u8; HASH_LENGTH],
+    pub(crate) channel_id: [u8; CHANNEL_ID_LENGTH],
+    pub(crate) block_height: u64,
+    pub(crate) block_timestamp: u64,
+    pub(crate) accounts: HashMap<PublicKey, String>,
+    pub(crate) state: HashMap<Vec<u8>, Vec<u8>>,
+    pub(crate) query_result: Option<Vec<u8>>,
+    pub(crate) insert_result: Result<u32, u32>,
+    pub(crate) tables: MemoryEngine,
+    pub(crate) persistence: persistence::Context,
+    pub(crate) calls: Vec<HostCall>,
+}
+
+impl Default for MockHost {
+    fn default() -> Self {
+        MockHost::new()
+    }
+}
+
+impl MockHost {
+    /// Empty mock host with a zero sender that is not the owner.
+    pub fn new() -> Self {
+        MockHost {
+            arguments: vec![],
+            sender: PublicKey::default(),
+            owner: false,
+            hash: [0u8; HASH_LENGTH],
+            channel_id: [0u8; CHANNEL_ID_LENGTH],
+            block_height: 0,
+            block_timestamp: 0,
+            accounts: HashMap::new(),
+            state: HashMap::new(),
+            query_result: None,
+            insert_result: Ok(0),
+            tables: MemoryEngine::new(),
+            persistence: persistence::Context::default(),
+            calls: vec![],
+        }
+    }
+
+    /// Set the bytes returned by `transaction::arguments`
+    pub fn arguments(mut self, arguments: Vec<u8>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    /// Set the key returned by `transaction::sender`
+    pub fn sender(mut self, sender: PublicKey) -> Self {
+        self.sender = sender;
+        self
+    }
+
+    /// Set whether `account::is_owner` is true
+    pub fn owner(mut self, owner: bool) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Set the hash returned by `transaction::hash`
+    pub fn hash(mut self, hash: [u8; HASH_LENGTH]) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Set the id returned by `transaction::channel_id`
+    pub fn channel_id(mut self, channel_id: [u8; CHANNEL_ID_LENGTH]) -> Self {
+        self.channel_id = channel_id;
+        self
+    }
+
+    /// Set the height returned by `transaction::block_height`
+    pub fn block_height(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
+    }
+
+    /// Set the timestamp returned by `transaction::block_timestamp`
+    pub fn block_timestamp(mut self, block_timestamp: u64) -> Self {
+        self.block_timestamp = block_timestamp;
+        self
+    }
+
+    /// Register the name returned by `account::name` for an account
+    pub fn account_name(mut self, key: PublicKey, name: &str) -> Self {
+        self.accounts.insert(key, name.to_string());
+        self
+    }
+
+    /// Store a value in the contract state
+    pub fn state(mut self, key: &[u8], value: &[u8]) -> Self {
+        self.state.insert(key.to_vec(), value.to_vec());
+        self
+    }
+
+    /// Set a fixed result returned by the sql query functions instead of
+    /// evaluating queries against the mock tables
+    pub fn query_result(mut self, result: &ResultSet) -> Self {
+        self.query_result = Some(result.to_xdr().unwrap());
+        self
+    }
+
+    /// `Err(code)` makes `sql::insert` fail with the code instead of inserting
+    pub fn insert_result(mut self, result: Result<u32, u32>) -> Self {
+        self.insert_result = result;
+        self
+    }
+
+    /// Set the in-memory tables the sql functions read and write
+    pub fn tables(mut self, tables: MemoryEngine) -> Self {
+        self.tables = tables;
+        self
+    }
+
+    /// Set the storage quota enforced by `persistence::store`
+    pub fn quota(mut self, quota: Quota) -> Self {
+        self.persistence.quota = quota;
+        self
+    }
+
+    /// Set the maximum size of a value read by `persistence::get`
+    pub fn max_value_size(mut self, size: usize) -> Self {
+        self.persistence.max_value_size = size;
+        self
+    }
+
+    /// Returns the value stored in the contract state at a key
+    pub fn stored(&self, key: &[u8]) -> Option<&[u8]> {
+        self.state.get(key).map(|value| value.as_slice())
+    }
+
+    /// Returns every call recorded by the mock host, in order
+    pub fn calls(&self) -> &[HostCall] {
+        &self.calls
+    }
+
+    /// Forget the recorded calls
+    pub fn clear_calls(&mut self) {
+        self.calls.clear();
+    }
+
+    /// Returns the bytes of the last `ret` call
+    pub fn returned(&self) -> Option<&[u8]> {
+        self.calls.iter().rev().find_map(|call| match call {
+            HostCall::Ret(values) => Some(values.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Returns the messages written by `log`, in order
+    pub fn logs(&self) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter_map(|call| match call {
+                HostCall::Log(msg) => Some(msg.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the messages reported by the panic hook, in order
+    pub fn errors(&self) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter_map(|call| match call {
+                HostCall::LogError(msg) => Some(msg.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Install the mock host on the current thread.
+    ///
+    /// The previous mock host is restored when the returned guard is dropped.
+    pub fn install(self) -> MockGuard {
+        MockGuard {
+            previous: Some(replace(self)),
+        }
+    }
+
+    /// Run `f` with the mock host installed on the current thread and return
+    /// the result of `f` with the mock host as it was left by `f`.
+    pub fn scoped<R, F: FnOnce() -> R>(self, f: F) -> (R, MockHost) {
+        let mut guard = self.install();
+        let result = f();
+        let previous = guard.previous.take().unwrap();
+        (result, replace(previous))
+    }
+}
+
+/// Restores the previously installed mock host when dropped.
+pub struct MockGuard {
+    previous: Option<MockHost>,
+}
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            replace(previous);
+        }
+    }
+}
+
+fn replace(mock: MockHost) -> MockHost {
+    MOCK.with(|current| mem::replace(&mut *current.borrow_mut(), mock))
+}
+
+/// Replace the mock host of the current thread with an empty one.
+pub fn reset() {
+    replace(MockHost::new());
+}
+
+/// Run `f` with the mock host of the current thread.
+///
+/// # Panics
+///
+/// If called from within `f` of another `with`.
+pub fn with<R, F: FnOnce(&mut MockHost) -> R>(f: F) -> R {
+    MOCK.with(|current| f(&mut current.borrow_mut()))
+}
+
+// Record a call, dropped if the mock host is in use, as when a panic
+// inside `with` reaches the panic hook.
+pub(crate) fn record(call: HostCall) {
+    let _ = MOCK.try_with(|current| {
+        if let Ok(mut mock) = current.try_borrow_mut() {
+            mock.calls.push(call);
+        }
+    });
+}
+
+/// Returns the calls recorded by the mock host of the current thread.
+pub fn calls() -> Vec<HostCall> {
+    with(|mock| mock.calls.clone())
+}
+
+/// Returns and forgets the calls recorded by the mock host of the current thread.
+pub fn take_calls() -> Vec<HostCall> {
+    with(|mock| mem::take(&mut mock.calls))
+}
+
+/// Assert that the mock host of the current thread recorded the call.
+#[track_caller]
+pub fn assert_called(call: &HostCall) {
+    with(|mock| {
+        assert!(
+            mock.calls.contains(call),
+            "{:?} was not called, recorded calls: {:?}",
+            call,
+            mock.calls
+        )
+    });
+}
+
+/// Assert that the last `ret` call returned the bytes.
+#[track_caller]
+pub fn assert_returned(values: &[u8]) {
+    with(|mock| {
+        assert_eq!(
+            Some(values),
+            mock.returned(),
+            "unexpected return value, recorded calls: {:?}",
+            mock.calls
+        )
+    });
+}
+
+/// Assert that `log` wrote the message.
+#[track_caller]
+pub fn assert_logged(msg: &str) {
+    assert_called(&HostCall::Log(msg.to_string()));
+}
+
+/// Assert that the value was stored at the key.
+#[track_caller]
+pub fn assert_stored(key: &[u8], value: &[u8]) {
+    assert_called(&HostCall::Store {
+        key: key.to_vec(),
+        value: value.to_vec(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use external::{account, errors, log, persistence, sql, transaction, ExternalError};
+
+    #[test]
+    fn test_builder() {
+        let alice = PublicKey::new([1u8; 32]);
+        let _mock = MockHost::new()
+            .sender(alice)
+            .owner(true)
+            .arguments(vec![1, 2])
+            .state(b"key", b"value")
+            .account_name(alice, "alice")
+            .install();
+
+        assert_eq!(alice, transaction::sender());
+        assert!(account::is_owner(alice));
+        assert_eq!(vec![1, 2], transaction::arguments());
+        assert_eq!(Ok(b"value".to_vec()), persistence::get(b"key".to_vec()));
+        assert_eq!(Ok("alice".to_string()), account::name(alice));
+    }
+
+    #[test]
+    fn test_install_restores() {
+        let _outer = MockHost::new().state(b"key", b"outer").install();
+        {
+            let _inner = MockHost::new().install();
+            assert_eq!(
+                Err(ExternalError::MissingKeyError),
+                persistence::get(b"key".to_vec())
+            );
+        }
+        assert_eq!(Ok(b"outer".to_vec()), persistence::get(b"key".to_vec()));
+    }
+
+    #[test]
+    fn test_scoped() {
+        let (owner, mock) = MockHost::new().owner(true).scoped(|| {
+            persistence::store(b"key".to_vec(), b"value".to_vec()).unwrap();
+            account::is_owner(PublicKey::default())
+        });
+        assert!(owner);
+        assert_eq!(Some(&b"value"[..]), mock.stored(b"key"));
+        assert!(!account::is_owner(PublicKey::default()));
+    }
+
+    #[test]
+    fn test_reset() {
+        persistence::store(b"key".to_vec(), b"value".to_vec()).unwrap();
+        reset();
+        assert_eq!(
+            Err(ExternalError::MissingKeyError),
+            persistence::get(b"key".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_record_calls() {
+        transaction::ret(vec![1]);
+        log("first".to_string());
+        persistence::store(b"key".to_vec(), b"value".to_vec()).unwrap();
+        persistence::delete(b"key".to_vec()).unwrap();
+        let _ = sql::insert("users".to_string(), "{".to_string());
+        transaction::ret(vec![2]);
+
+        assert_returned(&[2]);
+        assert_logged("first");
+        assert_stored(b"key", b"value");
+        assert_called(&HostCall::Delete {
+            key: b"key".to_vec(),
+        });
+        assert_called(&HostCall::Insert {
+            table: "users".to_string(),
+            json: "{".to_string(),
+        });
+        with(|mock| {
+            assert_eq!(HostCall::Ret(vec![1]), mock.calls()[0]);
+            assert_eq!(vec!["first"], mock.logs());
+            assert!(mock.errors().is_empty());
+        });
+
+        assert!(!take_calls().is_empty());
+        assert!(calls().is_empty());
+    }
+
+    #[test]
+    fn test_record_panic() {
+        // the hook is global, the previous one is restored before asserting
+        // since set_hook can not be called while unwinding
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(errors::hook));
+        let result = std::panic::catch_unwind(|| panic!("contract failed"));
+        std::panic::set_hook(previous);
+        assert!(result.is_err());
+        with(|mock| assert!(mock.errors()[0].contains("contract failed")));
+    }
+
+    #[test]
+    #[should_panic(expected = "was not called")]
+    fn test_assert_called() {
+        assert_logged("missing");
+    }
+}
@@ -18,5 +18,9 @@ pub mod transaction;
 pub mod errors;
 pub use self::errors::ExternalError;
 
-mod log;
+/// Thread-local mock host used by the functions above with `host-mock`.
+#[cfg(feature = "host-mock")]
+pub mod mock;
+
+pub(crate) mod log;
 pub use self::log::log;
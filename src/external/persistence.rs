@@ -1,12 +1,131 @@
 //! Provides access to the contract state to store and get key values
 
 #[cfg(not(feature = "host-mock"))]
-use super::externs::{_delete, _get, _get_length, _key_exists, _store};
+use super::externs::{_delete, _get, _get_length, _get_range, _key_exists, _store};
 
 #[cfg(feature = "host-mock")]
-pub static mut STORE: Option<std::collections::HashMap<Vec<u8>, Vec<u8>>> = None;
+use super::mock::{self, HostCall};
 
+use super::transaction;
 use super::ExternalError;
+use crypto::PublicKey;
+use host::with_host;
+use json;
+#[cfg(not(feature = "host-mock"))]
+use std::cell::Cell;
+use std::io::Write;
+use xdr_rs_serialize::de::XDRIn;
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::XDROut;
+
+/// Default maximum value size, values of any length can be read.
+pub const DEFAULT_MAX_VALUE_SIZE: usize = usize::MAX;
+
+// Limits and usage of the current transaction, kept by the mock host when
+// built with the `host-mock` feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Context {
+    pub(crate) max_value_size: usize,
+    pub(crate) quota: Quota,
+    pub(crate) usage: StorageUsage,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            quota: Quota::default(),
+            usage: StorageUsage::default(),
+        }
+    }
+}
+
+#[cfg(not(feature = "host-mock"))]
+thread_local! {
+    static CONTEXT: Cell<Context> = Cell::new(Context::default());
+}
+
+#[cfg(not(feature = "host-mock"))]
+fn with_context<R, F: FnOnce(&mut Context) -> R>(f: F) -> R {
+    CONTEXT.with(|cell| {
+        let mut context = cell.get();
+        let result = f(&mut context);
+        cell.set(context);
+        result
+    })
+}
+
+#[cfg(feature = "host-mock")]
+fn with_context<R, F: FnOnce(&mut Context) -> R>(f: F) -> R {
+    mock::with(|mock| f(&mut mock.persistence))
+}
+
+/// Set the maximum size in bytes of a value that `get` will allocate for.
+///
+/// Reading a value larger than this returns `ExternalError::ValueSizeError`
+/// instead of allocating a buffer for it.
+///
+/// # Arguments
+///
+/// * `size` - The maximum value size in bytes
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::persistence;
+/// persistence::set_max_value_size(64 * 1024);
+/// ```
+pub fn set_max_value_size(size: usize) {
+    with_context(|context| context.max_value_size = size);
+}
+
+/// Returns the currently configured maximum value size in bytes.
+pub fn max_value_size() -> usize {
+    with_context(|context| context.max_value_size)
+}
+
+/// Get the length of the value associated with a key without fetching the value.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key of the value
+///
+/// # Returns
+///
+/// Result<u32, ExternalError>
+/// * `u32` - The length in bytes of the value stored in state
+/// * `ExternalError` - Error if the key is not found in state
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::persistence;
+/// let len = persistence::get_length(vec![0u8, 1, 2, 3]);
+/// ```
+pub fn get_length(key: Vec<u8>) -> Result<u32, ExternalError> {
+    with_host(|host| host.get_length(&key))
+}
+
+// Raw host operations used by `HostImpl`, without the value size limit,
+// quota and usage accounting of the public functions.
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_get_length(key: &[u8]) -> Result<u32, ExternalError> {
+    let exists = unsafe { _key_exists(key.as_ptr(), key.len()) };
+    if exists {
+        Ok(unsafe { _get_length(key.as_ptr(), key.len()) })
+    } else {
+        Err(ExternalError::MissingKeyError)
+    }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_get_length(key: &[u8]) -> Result<u32, ExternalError> {
+    mock::with(|mock| match mock.state.get(key) {
+        Some(val) => Ok(val.len() as u32),
+        None => Err(ExternalError::MissingKeyError),
+    })
+}
 
 /// Get the value associated with a string key from the persistent storage for this runtime.
 ///
@@ -19,6 +138,7 @@ use super::ExternalError;
 /// Result<Vec<u8>, ExternalError>
 /// * `Vec<u8>` - The value stored in state if found
 /// * `ExternalError` - Error if there is a problem getting the value stored in state
+///   or the value is larger than the configured maximum value size
 ///
 /// # Example
 ///
@@ -26,35 +146,336 @@ use super::ExternalError;
 /// use mazzaroth_rs::persistence;
 /// let value = persistence::get(vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
 /// ```
-#[cfg(not(feature = "host-mock"))]
 pub fn get(key: Vec<u8>) -> Result<Vec<u8>, ExternalError> {
-    let exists = unsafe { _key_exists(key.as_ptr(), key.len()) };
-    if exists {
-        let len = unsafe { _get_length(key.as_ptr(), key.len()) };
-        let mut val = Vec::with_capacity(len as usize);
-        unsafe { val.set_len(len as usize) };
-        unsafe { _get(key.as_ptr(), key.len(), val.as_mut_ptr()) };
-        Ok(val)
-    } else {
-        Err(ExternalError::MissingKeyError)
+    let max_value_size = max_value_size();
+    with_host(|host| {
+        // without a limit the host allocates the value without asking its length first
+        if max_value_size != DEFAULT_MAX_VALUE_SIZE
+            && host.get_length(&key)? as usize > max_value_size
+        {
+            return Err(ExternalError::ValueSizeError);
+        }
+        host.get(&key)
+    })
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_get(key: &[u8]) -> Result<Vec<u8>, ExternalError> {
+    let mut val = vec![0u8; raw_get_length(key)? as usize];
+    unsafe { _get(key.as_ptr(), key.len(), val.as_mut_ptr()) };
+    Ok(val)
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_get(key: &[u8]) -> Result<Vec<u8>, ExternalError> {
+    mock::with(|mock| match mock.state.get(key) {
+        Some(val) => Ok(val.clone()),
+        None => Err(ExternalError::MissingKeyError),
+    })
+}
+
+/// Read the value associated with a key into a caller provided buffer.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key used to get a value from state
+/// * `buf` - The buffer the value is copied into, must be at least the length of the value
+///
+/// # Returns
+///
+/// Result<usize, ExternalError>
+/// * `usize` - The number of bytes written to the front of `buf`
+/// * `ExternalError` - Error if the key is not found or `buf` is too small for the value
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::persistence;
+/// let mut buf = [0u8; 64];
+/// let len = persistence::get_into(vec![0u8, 1, 2, 3], &mut buf)?;
+/// let value = &buf[..len];
+/// ```
+pub fn get_into(key: Vec<u8>, buf: &mut [u8]) -> Result<usize, ExternalError> {
+    with_host(|host| host.get_into(&key, buf))
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_get_into(key: &[u8], buf: &mut [u8]) -> Result<usize, ExternalError> {
+    let len = raw_get_length(key)? as usize;
+    if len > buf.len() {
+        return Err(ExternalError::BufferSizeError);
     }
+    unsafe { _get(key.as_ptr(), key.len(), buf.as_mut_ptr()) };
+    Ok(len)
 }
 
 #[cfg(feature = "host-mock")]
-pub fn get(key: Vec<u8>) -> Result<Vec<u8>, ExternalError> {
-    unsafe {
-        match STORE {
-            Some(ref store) => match store.get(&key) {
-                Some(ref val) => Ok(val.clone().to_vec()),
-                None => Err(ExternalError::MissingKeyError),
-            },
-            None => Err(ExternalError::MissingKeyError),
+pub(crate) fn raw_get_into(key: &[u8], buf: &mut [u8]) -> Result<usize, ExternalError> {
+    mock::with(|mock| match mock.state.get(key) {
+        Some(val) => {
+            if val.len() > buf.len() {
+                return Err(ExternalError::BufferSizeError);
+            }
+            buf[..val.len()].copy_from_slice(val);
+            Ok(val.len())
+        }
+        None => Err(ExternalError::MissingKeyError),
+    })
+}
+
+/// Get a byte sub-range of the value associated with a key.
+///
+/// Only the requested bytes are copied from the host.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key used to get a value from state
+/// * `offset` - The offset of the first byte to read
+/// * `length` - The number of bytes to read
+///
+/// # Returns
+///
+/// Result<Vec<u8>, ExternalError>
+/// * `Vec<u8>` - The bytes of the value in `offset..offset + length`
+/// * `ExternalError` - Error if the key is not found, the range is outside of the value,
+///   the host copies fewer bytes than requested or the length is larger than the
+///   configured maximum value size
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::persistence;
+/// let header = persistence::get_range(vec![0u8, 1, 2, 3], 0, 8);
+/// ```
+pub fn get_range(key: Vec<u8>, offset: u32, length: u32) -> Result<Vec<u8>, ExternalError> {
+    if length as usize > max_value_size() {
+        return Err(ExternalError::ValueSizeError);
+    }
+    with_host(|host| host.get_range(&key, offset, length))
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_get_range(
+    key: &[u8],
+    offset: u32,
+    length: u32,
+) -> Result<Vec<u8>, ExternalError> {
+    let len = raw_get_length(key)?;
+    match offset.checked_add(length) {
+        Some(end) if end <= len => {}
+        _ => return Err(ExternalError::RangeError),
+    }
+    let mut val = vec![0u8; length as usize];
+    let copied =
+        unsafe { _get_range(key.as_ptr(), key.len(), offset, val.as_mut_ptr(), val.len()) };
+    // a short copy would leave the tail of the range zeroed
+    if copied != length {
+        return Err(ExternalError::RangeError);
+    }
+    Ok(val)
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_get_range(
+    key: &[u8],
+    offset: u32,
+    length: u32,
+) -> Result<Vec<u8>, ExternalError> {
+    mock::with(|mock| match mock.state.get(key) {
+        Some(val) => {
+            let start = offset as usize;
+            let end = match start.checked_add(length as usize) {
+                Some(end) if end <= val.len() => end,
+                _ => return Err(ExternalError::RangeError),
+            };
+            Ok(val[start..end].to_vec())
+        }
+        None => Err(ExternalError::MissingKeyError),
+    })
+}
+
+/// Reserved key holding the cumulative storage usage of the contract.
+///
+/// `store` and `delete` reject this key and keys starting with
+/// `ACCOUNT_USAGE_PREFIX`.
+pub const USAGE_KEY: &[u8] = b"__mazzaroth_storage_usage";
+
+/// Reserved key prefix holding the cumulative storage usage of each account,
+/// followed by the account public key.
+pub const ACCOUNT_USAGE_PREFIX: &[u8] = b"__mazzaroth_storage_usage/";
+
+/// Counts of the bytes and keys added to and removed from state.
+///
+/// Writes are only counted while a quota is set with `set_quota`. Overwriting
+/// a value only counts the difference in length, so rewriting a key with a
+/// value of the same length changes nothing.
+#[derive(PartialEq, Clone, Copy, Default, Debug, XDROut, XDRIn)]
+pub struct StorageUsage {
+    /// Number of value bytes added by `store`
+    pub bytes_written: u64,
+    /// Number of keys created by `store`
+    pub keys_written: u64,
+    /// Number of value bytes removed by `delete` or by `store` with a shorter value
+    pub bytes_deleted: u64,
+    /// Number of keys removed by `delete`
+    pub keys_deleted: u64,
+}
+
+impl StorageUsage {
+    /// Bytes written less bytes deleted.
+    pub fn net_bytes(&self) -> u64 {
+        self.bytes_written.saturating_sub(self.bytes_deleted)
+    }
+
+    // `previous` is the length of the overwritten value, `None` for a new key
+    fn record_store(&mut self, previous: Option<u64>, len: u64) {
+        match previous {
+            Some(previous) if previous > len => self.bytes_deleted += previous - len,
+            Some(previous) => self.bytes_written += len - previous,
+            None => {
+                self.bytes_written += len;
+                self.keys_written += 1;
+            }
         }
     }
+
+    fn record_delete(&mut self, len: u64) {
+        self.bytes_deleted += len;
+        self.keys_deleted += 1;
+    }
+}
+
+/// Storage limits enforced by `store`.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Quota {
+    /// Maximum size in bytes of a single stored value
+    pub per_key: Option<u64>,
+    /// Maximum net bytes an account may hold in state, attributed to the transaction sender
+    pub per_account: Option<u64>,
+}
+
+/// Set the storage quota enforced by `store` for the rest of the transaction.
+///
+/// While a quota is set `store` and `delete` also count their writes in the
+/// transaction, contract and sender storage usage.
+///
+/// # Arguments
+///
+/// * `quota` - The per key and per account limits, `None` disables a limit
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::persistence;
+/// persistence::set_quota(persistence::Quota {
+///     per_key: Some(1024),
+///     per_account: Some(64 * 1024),
+/// });
+/// ```
+pub fn set_quota(quota: Quota) {
+    with_context(|context| context.quota = quota);
+}
+
+/// Returns the currently configured storage quota.
+pub fn quota() -> Quota {
+    with_context(|context| context.quota)
+}
+
+/// Returns the storage usage of the current transaction, counted while a quota is set.
+pub fn usage() -> StorageUsage {
+    with_context(|context| context.usage)
+}
+
+/// Returns the cumulative storage usage of the contract across all
+/// transactions, counted while a quota is set.
+///
+/// Fails with `ExternalError::DecodeError` if the stored counters are corrupt.
+pub fn total_usage() -> Result<StorageUsage, ExternalError> {
+    read_usage(USAGE_KEY.to_vec())
+}
+
+/// Returns the cumulative storage usage attributed to an account, counted
+/// while a quota is set.
+///
+/// Fails with `ExternalError::DecodeError` if the stored counters are corrupt.
+///
+/// # Arguments
+///
+/// * `key` - The public key of the account
+pub fn account_usage(key: PublicKey) -> Result<StorageUsage, ExternalError> {
+    read_usage(account_usage_key(key))
+}
+
+pub(crate) fn is_reserved(key: &[u8]) -> bool {
+    key == USAGE_KEY || key.starts_with(ACCOUNT_USAGE_PREFIX)
+}
+
+fn account_usage_key(key: PublicKey) -> Vec<u8> {
+    let mut usage_key = ACCOUNT_USAGE_PREFIX.to_vec();
+    usage_key.extend_from_slice(key.as_bytes());
+    usage_key
+}
+
+// Usage is only counted while a quota is set, so contracts without one
+// only write their values
+fn counted(quota: Quota) -> bool {
+    quota != Quota::default()
+}
+
+// Counters that were never written are zero
+fn read_usage(key: Vec<u8>) -> Result<StorageUsage, ExternalError> {
+    match get(key) {
+        Ok(bytes) => match StorageUsage::read_xdr(&bytes) {
+            Ok((usage, read)) if read == bytes.len() as u64 => Ok(usage),
+            _ => Err(ExternalError::DecodeError),
+        },
+        Err(ExternalError::MissingKeyError) => Ok(StorageUsage::default()),
+        Err(err) => Err(err),
+    }
+}
+
+fn write_usage(key: &[u8], usage: StorageUsage) -> Result<(), ExternalError> {
+    let mut bytes = Vec::new();
+    // writing fixed size integers to a Vec cannot fail
+    usage.write_xdr(&mut bytes).unwrap();
+    with_host(|host| host.store(key, &bytes))
+}
+
+// Contract and account usage counters read before changing state, so that
+// corrupt counters fail the change before anything is written
+struct UsageCounters {
+    total: StorageUsage,
+    account_key: Vec<u8>,
+    account: StorageUsage,
+}
+
+impl UsageCounters {
+    fn read() -> Result<Self, ExternalError> {
+        let account_key = account_usage_key(transaction::sender());
+        Ok(UsageCounters {
+            total: total_usage()?,
+            account: read_usage(account_key.clone())?,
+            account_key,
+        })
+    }
+
+    // Record a change in the transaction, contract and account usage counters
+    fn record<F: Fn(&mut StorageUsage)>(mut self, record: F) -> Result<(), ExternalError> {
+        record(&mut self.total);
+        record(&mut self.account);
+        with_context(|context| record(&mut context.usage));
+        write_usage(USAGE_KEY, self.total)?;
+        write_usage(&self.account_key, self.account)
+    }
 }
 
 /// Store a key/value pair in the persistent storage for this runtime.
 ///
+/// Without a quota this only writes the value. While a quota is set the write
+/// is checked against it and counted in the transaction, contract and sender
+/// storage usage: this also reads the length of the previous value, fetches
+/// the sender and reads and writes the contract and sender usage counters.
+///
 /// # Arguments
 ///
 /// * `key` - The Vec<u8> key used to store a value in state
@@ -62,7 +483,10 @@ pub fn get(key: Vec<u8>) -> Result<Vec<u8>, ExternalError> {
 ///
 /// # Returns
 ///
-/// * `None`
+/// Result<(), ExternalError>
+/// * `Void` - simply returns OK if the value was stored.
+/// * `ExternalError` - Error if the key is reserved, storing the value would exceed
+///   the configured quota or the usage counters cannot be decoded
 ///
 /// # Example
 ///
@@ -70,28 +494,57 @@ pub fn get(key: Vec<u8>) -> Result<Vec<u8>, ExternalError> {
 /// use mazzaroth_rs::persistence;
 /// persistence::store(vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10], vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
 /// ```
+pub fn store(key: Vec<u8>, val: Vec<u8>) -> Result<(), ExternalError> {
+    if is_reserved(&key) {
+        return Err(ExternalError::ReservedKeyError);
+    }
+    let quota = quota();
+    if !counted(quota) {
+        return with_host(|host| host.store(&key, &val));
+    }
+    let len = val.len() as u64;
+    if let Some(max) = quota.per_key {
+        if len > max {
+            return Err(ExternalError::QuotaExceededError);
+        }
+    }
+
+    let previous = match get_length(key.clone()) {
+        Ok(previous) => Some(u64::from(previous)),
+        Err(ExternalError::MissingKeyError) => None,
+        Err(err) => return Err(err),
+    };
+    let counters = UsageCounters::read()?;
+    if let Some(max) = quota.per_account {
+        let growth = len.saturating_sub(previous.unwrap_or(0));
+        if counters.account.net_bytes().saturating_add(growth) > max {
+            return Err(ExternalError::QuotaExceededError);
+        }
+    }
+
+    with_host(|host| host.store(&key, &val))?;
+    counters.record(|usage| usage.record_store(previous, len))
+}
+
 #[cfg(not(feature = "host-mock"))]
-pub fn store(key: Vec<u8>, val: Vec<u8>) {
+pub(crate) fn raw_store(key: &[u8], val: &[u8]) {
     unsafe { _store(key.as_ptr(), key.len(), val.as_ptr(), val.len()) };
 }
 
 #[cfg(feature = "host-mock")]
-pub fn store(key: Vec<u8>, val: Vec<u8>) {
-    unsafe {
-        match STORE {
-            Some(ref mut store) => {
-                store.insert(key, val);
-            }
-            None => {
-                STORE = Some(std::collections::HashMap::new());
-                store(key, val);
-            }
-        }
-    }
+pub(crate) fn raw_store(key: &[u8], val: &[u8]) {
+    mock::with(|mock| mock.state.insert(key.to_vec(), val.to_vec()));
+    mock::record(HostCall::Store {
+        key: key.to_vec(),
+        value: val.to_vec(),
+    });
 }
 
 /// Delete a key from the contract state.
 ///
+/// While a quota is set the deleted bytes are counted in the transaction,
+/// contract and sender storage usage.
+///
 /// # Arguments
 ///
 /// * `key` - The Vec<u8> key to be deleted
@@ -100,7 +553,8 @@ pub fn store(key: Vec<u8>, val: Vec<u8>) {
 ///
 /// Result<(), ExternalError>
 /// * `Void` - simply returns OK if the delete was successful.
-/// * `ExternalError` - Error if there is a problem deleting the value stored in state
+/// * `ExternalError` - Error if the key is not found or reserved, or the usage
+///   counters cannot be decoded
 ///
 /// # Example
 ///
@@ -108,8 +562,21 @@ pub fn store(key: Vec<u8>, val: Vec<u8>) {
 /// use mazzaroth_rs::persistence;
 /// persistence::delete(vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
 /// ```
-#[cfg(not(feature = "host-mock"))]
 pub fn delete(key: Vec<u8>) -> Result<(), ExternalError> {
+    if is_reserved(&key) {
+        return Err(ExternalError::ReservedKeyError);
+    }
+    if !counted(quota()) {
+        return with_host(|host| host.delete(&key));
+    }
+    let len = u64::from(get_length(key.clone())?);
+    let counters = UsageCounters::read()?;
+    with_host(|host| host.delete(&key))?;
+    counters.record(|usage| usage.record_delete(len))
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_delete(key: &[u8]) -> Result<(), ExternalError> {
     let exists = unsafe { _key_exists(key.as_ptr(), key.len()) };
     if exists {
         unsafe { _delete(key.as_ptr(), key.len()) };
@@ -120,21 +587,20 @@ pub fn delete(key: Vec<u8>) -> Result<(), ExternalError> {
 }
 
 #[cfg(feature = "host-mock")]
-pub fn delete(key: Vec<u8>) -> Result<(), ExternalError> {
-    unsafe {
-        match STORE {
-            Some(ref mut store) => match store.remove(&key) {
-                Some(_) => Ok(()),
-                None => Err(ExternalError::MissingKeyError),
-            },
-            None => Err(ExternalError::MissingKeyError),
+pub(crate) fn raw_delete(key: &[u8]) -> Result<(), ExternalError> {
+    match mock::with(|mock| mock.state.remove(key)) {
+        Some(_) => {
+            mock::record(HostCall::Delete { key: key.to_vec() });
+            Ok(())
         }
+        None => Err(ExternalError::MissingKeyError),
     }
 }
 
 #[cfg(test)]
 #[cfg(feature = "host-mock")]
 mod tests {
+    use super::super::mock::MockHost;
     use super::*;
 
     #[test]
@@ -144,14 +610,191 @@ mod tests {
 
     #[test]
     fn test_get() {
-        store(vec![1, 2], vec![1, 1, 1, 1]);
+        store(vec![1, 2], vec![1, 1, 1, 1]).unwrap();
         assert_eq!(Ok(vec![1, 1, 1, 1]), get(vec![1, 2]));
     }
 
+    #[test]
+    fn test_get_length() {
+        store(vec![4, 2], vec![1, 2, 3]).unwrap();
+        assert_eq!(Ok(3), get_length(vec![4, 2]));
+        assert_eq!(Err(ExternalError::MissingKeyError), get_length(vec![4, 3]));
+    }
+
+    #[test]
+    fn test_get_into() {
+        store(vec![5, 2], vec![1, 2, 3]).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(Ok(3), get_into(vec![5, 2], &mut buf));
+        assert_eq!([1, 2, 3, 0], buf);
+        let mut small = [0u8; 2];
+        assert_eq!(
+            Err(ExternalError::BufferSizeError),
+            get_into(vec![5, 2], &mut small)
+        );
+    }
+
+    #[test]
+    fn test_get_range() {
+        store(vec![6, 2], vec![1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(Ok(vec![2, 3, 4]), get_range(vec![6, 2], 1, 3));
+        assert_eq!(Ok(vec![]), get_range(vec![6, 2], 5, 0));
+        assert_eq!(Err(ExternalError::RangeError), get_range(vec![6, 2], 3, 3));
+        assert_eq!(
+            Err(ExternalError::RangeError),
+            get_range(vec![6, 2], u32::MAX, 2)
+        );
+    }
+
     #[test]
     fn test_get_delete() {
-        store(vec![3, 2], vec![1, 1, 1, 1]);
+        store(vec![3, 2], vec![1, 1, 1, 1]).unwrap();
         delete(vec![3, 2]).unwrap();
         assert_eq!(Err(ExternalError::MissingKeyError), get(vec![3, 2]));
     }
+
+    // Quota large enough to count usage without limiting writes
+    fn count_usage() {
+        set_quota(Quota {
+            per_key: Some(u64::MAX),
+            per_account: None,
+        });
+    }
+
+    #[test]
+    fn test_uncounted_writes() {
+        store(vec![15, 2], vec![1, 2, 3]).unwrap();
+        delete(vec![15, 2]).unwrap();
+        assert_eq!(StorageUsage::default(), usage());
+        assert_eq!(Ok(StorageUsage::default()), total_usage());
+        mock::with(|mock| {
+            assert_eq!(2, mock.calls().len());
+            assert!(!mock.state.contains_key(USAGE_KEY));
+        });
+    }
+
+    #[test]
+    fn test_usage() {
+        count_usage();
+        let before = usage();
+        store(vec![7, 2], vec![1, 2, 3]).unwrap();
+        store(vec![7, 3], vec![1, 2]).unwrap();
+        delete(vec![7, 2]).unwrap();
+        let after = usage();
+        assert_eq!(before.bytes_written + 5, after.bytes_written);
+        assert_eq!(before.keys_written + 2, after.keys_written);
+        assert_eq!(before.bytes_deleted + 3, after.bytes_deleted);
+        assert_eq!(before.keys_deleted + 1, after.keys_deleted);
+        assert!(total_usage().unwrap().bytes_written >= 5);
+    }
+
+    #[test]
+    fn test_overwrite_usage() {
+        count_usage();
+        store(vec![12, 2], vec![1, 2, 3]).unwrap();
+        store(vec![12, 2], vec![4, 5, 6]).unwrap();
+        let usage = account_usage(PublicKey::default()).unwrap();
+        assert_eq!(3, usage.net_bytes());
+        assert_eq!(1, usage.keys_written);
+
+        store(vec![12, 2], vec![1]).unwrap();
+        store(vec![12, 2], vec![1, 2]).unwrap();
+        let usage = account_usage(PublicKey::default()).unwrap();
+        assert_eq!(2, usage.net_bytes());
+        assert_eq!(1, usage.keys_written);
+        assert_eq!(usage, total_usage().unwrap());
+
+        // rewriting a key does not grow state, so fits a full quota
+        set_quota(Quota {
+            per_key: None,
+            per_account: Some(2),
+        });
+        assert_eq!(Ok(()), store(vec![12, 2], vec![3, 4]));
+        assert_eq!(
+            Err(ExternalError::QuotaExceededError),
+            store(vec![12, 3], vec![1])
+        );
+    }
+
+    #[test]
+    fn test_reserved_keys() {
+        count_usage();
+        assert_eq!(
+            Err(ExternalError::ReservedKeyError),
+            store(USAGE_KEY.to_vec(), vec![])
+        );
+        assert_eq!(
+            Err(ExternalError::ReservedKeyError),
+            store(account_usage_key(PublicKey::default()), vec![])
+        );
+        store(vec![13, 2], vec![1]).unwrap();
+        assert_eq!(
+            Err(ExternalError::ReservedKeyError),
+            delete(USAGE_KEY.to_vec())
+        );
+        assert!(get(USAGE_KEY.to_vec()).is_ok());
+    }
+
+    #[test]
+    fn test_corrupt_usage() {
+        let _mock = MockHost::new().state(USAGE_KEY, &[1, 2, 3]).install();
+        count_usage();
+        assert_eq!(Err(ExternalError::DecodeError), total_usage());
+        assert_eq!(Err(ExternalError::DecodeError), store(vec![14, 2], vec![1]));
+        assert_eq!(Err(ExternalError::MissingKeyError), get(vec![14, 2]));
+    }
+
+    #[test]
+    fn test_key_quota() {
+        set_quota(Quota {
+            per_key: Some(2),
+            per_account: None,
+        });
+        assert_eq!(Ok(()), store(vec![8, 2], vec![1, 2]));
+        assert_eq!(
+            Err(ExternalError::QuotaExceededError),
+            store(vec![8, 2], vec![1, 2, 3])
+        );
+        assert_eq!(Ok(vec![1, 2]), get(vec![8, 2]));
+    }
+
+    #[test]
+    fn test_account_quota() {
+        set_quota(Quota {
+            per_key: None,
+            per_account: Some(0),
+        });
+        assert_eq!(
+            Err(ExternalError::QuotaExceededError),
+            store(vec![9, 2], vec![1])
+        );
+        assert_eq!(Err(ExternalError::MissingKeyError), get(vec![9, 2]));
+    }
+
+    #[test]
+    fn test_max_value_size() {
+        store(vec![10, 2], vec![1, 2, 3]).unwrap();
+        set_max_value_size(2);
+        assert_eq!(Err(ExternalError::ValueSizeError), get(vec![10, 2]));
+        assert_eq!(Ok(vec![1, 2]), get_range(vec![10, 2], 0, 2));
+
+        // the limit belongs to the mock host
+        mock::reset();
+        assert_eq!(DEFAULT_MAX_VALUE_SIZE, max_value_size());
+        let _mock = MockHost::new().max_value_size(1).install();
+        assert_eq!(1, max_value_size());
+    }
+
+    #[test]
+    fn test_reset_usage() {
+        set_quota(Quota {
+            per_key: Some(2),
+            per_account: None,
+        });
+        store(vec![11, 2], vec![1, 2]).unwrap();
+        assert_eq!(2, usage().bytes_written);
+        mock::reset();
+        assert_eq!(StorageUsage::default(), usage());
+        assert_eq!(Quota::default(), quota());
+    }
 }
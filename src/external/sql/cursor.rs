@@ -0,0 +1,184 @@
+//! Page by page access to query results.
+//!
+//! `exec` and `query` fetch the entire result of a query in one allocation.
+//! A `Cursor` asks the host for at most `page_size` rows at a time instead, so
+//! large results can be processed without holding them in wasm memory at once.
+//! Every page carries an opaque continuation token the host uses to resume the
+//! query after the last row of the page. The token can be saved in state to
+//! continue a query in a later transaction with `Cursor::resume`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::external::sql::{Cursor, Query};
+//!
+//! let mut cursor = Cursor::new(&Query::select(&["*"]).from("users"))?.page_size(50);
+//! while let Some(users) = cursor.next_rows::<User>()? {
+//!     for user in users {
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use super::{FromRow, Query, QueryError, ResultSet, SqlError};
+
+use host::with_host;
+use json;
+use std::io::Write;
+use xdr_rs_serialize::de::{read_var_opaque, read_var_opaque_json, XDRIn};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{write_var_opaque, write_var_opaque_json, XDROut};
+
+/// Number of rows fetched per page when no page size is set.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// A page of query results as returned by the host.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct Page {
+    /// Rows of the page
+    pub result: ResultSet,
+    /// Continuation token of the next page, empty if this is the last page
+    #[array(var = 2147483647)]
+    pub token: Vec<u8>,
+}
+
+impl Page {
+    /// Decode a page from the XDR bytes returned by the host
+    pub fn from_xdr(bytes: &[u8]) -> Result<Self, SqlError> {
+        Ok(Page::read_xdr(bytes)?.0)
+    }
+
+    /// Encode the page as XDR bytes, the encoding returned by the host
+    pub fn to_xdr(&self) -> Result<Vec<u8>, SqlError> {
+        let mut bytes = Vec::new();
+        self.write_xdr(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// True if no rows follow this page
+    pub fn is_last(&self) -> bool {
+        self.token.is_empty()
+    }
+}
+
+/// Fetches the result of a query page by page.
+///
+/// Iterating a cursor yields one `ResultSet` per page and stops after the
+/// last page or the first error.
+#[derive(Clone, Debug)]
+pub struct Cursor {
+    query: String,
+    page_size: u32,
+    token: Vec<u8>,
+    done: bool,
+}
+
+impl Cursor {
+    /// Cursor over the result of a query built with `Query`
+    pub fn new(query: &Query) -> Result<Self, QueryError> {
+        Ok(Cursor::raw(query.render()?))
+    }
+
+    /// Cursor over the result of a string query
+    pub fn raw(query: String) -> Self {
+        Cursor {
+            query,
+            page_size: DEFAULT_PAGE_SIZE,
+            token: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Set the maximum number of rows fetched per page, at least 1
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Continue the query from a token returned by `token` on an earlier cursor
+    /// over the same query
+    pub fn resume(mut self, token: Vec<u8>) -> Self {
+        self.token = token;
+        self.done = false;
+        self
+    }
+
+    /// Continuation token of the next page.
+    ///
+    /// Empty before the first page is fetched and `None` once all pages have
+    /// been fetched.
+    pub fn token(&self) -> Option<&[u8]> {
+        if self.done {
+            None
+        } else {
+            Some(&self.token)
+        }
+    }
+
+    /// Fetch the next page of rows, `None` once all rows have been fetched
+    pub fn next_page(&mut self) -> Result<Option<ResultSet>, SqlError> {
+        if self.done {
+            return Ok(None);
+        }
+        let page = with_host(|host| host.query_page(&self.query, &self.token, self.page_size));
+        let page = match page {
+            Ok(page) => page,
+            Err(err) => {
+                self.done = true;
+                return Err(err);
+            }
+        };
+        self.done = page.is_last();
+        self.token = page.token;
+        if self.done && page.result.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(page.result))
+    }
+
+    /// Fetch the next page of rows and map every row into `T`
+    pub fn next_rows<T: FromRow>(&mut self) -> Result<Option<Vec<T>>, SqlError> {
+        match self.next_page()? {
+            Some(result) => Ok(Some(result.decode()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = Result<ResultSet, SqlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_page() {
+            Ok(page) => page.map(Ok),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_xdr_roundtrip() {
+        let mut result = ResultSet::new(vec!["name".to_string()]);
+        result.push_row(vec![r#""bob""#.to_string()]);
+        let page = Page {
+            result,
+            token: vec![0, 0, 0, 1],
+        };
+        let bytes = page.to_xdr().unwrap();
+        assert_eq!(Ok(page), Page::from_xdr(&bytes));
+    }
+
+    #[test]
+    fn test_cursor_builder() {
+        let cursor = Cursor::raw("SELECT * FROM users".to_string()).page_size(0);
+        assert_eq!(1, cursor.page_size);
+        assert_eq!(Some(&[][..]), cursor.token());
+
+        let cursor = cursor.resume(vec![1, 2]);
+        assert_eq!(Some(&[1, 2][..]), cursor.token());
+    }
+}
@@ -0,0 +1,805 @@
+//! In-memory kvquery engine.
+//!
+//! Stores inserted JSON rows per table and evaluates the subset of the query
+//! language rendered by `Query`:
+//!
+//! ```text
+//! SELECT * | column, ... FROM table
+//!     [WHERE condition]
+//!     [ORDER BY column [ASC | DESC], ...]
+//!     [LIMIT n]
+//! ```
+//!
+//! Conditions compare a column to a literal with `=`, `!=`, `<>`, `<`, `<=`,
+//! `>` or `>=` and can be combined with `AND`, `OR` and parentheses. Columns
+//! may use dots to address nested fields. Strings holding integers, as 64 bit
+//! integers are JSON encoded, compare numerically against integer literals and
+//! sort numerically.
+//!
+//! A table that was never inserted into is empty: selecting from it returns no
+//! rows and updating or deleting from it affects none.
+//!
+//! Query results are returned as the same `ResultSet` encoding used by the host.
+//! This engine backs the `host-mock` implementations of the sql functions.
+
+use super::{Comparison, Page, ResultSet};
+
+use json::JsonValue;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Defines the errors that can be returned when evaluating a query.
+#[derive(Debug, PartialEq)]
+pub enum ReadError {
+    /// The query could not be parsed
+    InvalidQuery,
+    /// The continuation token was not returned by an earlier page
+    InvalidToken,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::InvalidQuery => write!(f, "Query could not be parsed."),
+            ReadError::InvalidToken => write!(f, "Invalid continuation token."),
+        }
+    }
+}
+
+/// Defines the errors that can be returned when the tables reject a write.
+///
+/// Hosts only report a non zero code for a rejected write, the mock host and
+/// the runner translate these errors into such a code.
+#[derive(Debug, PartialEq)]
+pub enum TableError {
+    /// The JSON object could not be parsed
+    InvalidJson,
+    /// The JSON value is not an object
+    SchemaMismatch,
+    /// The filter could not be parsed
+    InvalidFilter,
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::InvalidJson => write!(f, "Row is not valid JSON."),
+            TableError::SchemaMismatch => write!(f, "Row does not match the table schema."),
+            TableError::InvalidFilter => write!(f, "Filter could not be parsed."),
+        }
+    }
+}
+
+// The query or filter could not be parsed
+#[derive(Debug)]
+struct ParseError;
+
+impl From<ParseError> for ReadError {
+    fn from(_: ParseError) -> Self {
+        ReadError::InvalidQuery
+    }
+}
+
+impl From<ParseError> for TableError {
+    fn from(_: ParseError) -> Self {
+        TableError::InvalidFilter
+    }
+}
+
+/// In-memory table store evaluating kvquery queries.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryEngine {
+    tables: BTreeMap<String, Vec<JsonValue>>,
+}
+
+impl MemoryEngine {
+    /// New engine without any tables
+    pub fn new() -> Self {
+        MemoryEngine::default()
+    }
+
+    /// Remove every table and row
+    pub fn clear(&mut self) {
+        self.tables.clear();
+    }
+
+    /// Rows inserted into a table, as JSON objects
+    pub fn rows(&self, table_name: &str) -> &[JsonValue] {
+        match self.tables.get(table_name) {
+            Some(rows) => rows,
+            None => &[],
+        }
+    }
+
+    /// Insert a JSON object into a table, creating the table if needed.
+    pub fn insert(&mut self, table_name: &str, json: &str) -> Result<(), TableError> {
+        let row = json::parse(json).map_err(|_| TableError::InvalidJson)?;
+        if !row.is_object() {
+            return Err(TableError::SchemaMismatch);
+        }
+        self.tables
+            .entry(table_name.to_string())
+            .or_default()
+            .push(row);
+        Ok(())
+    }
+
+    /// Set the fields of a JSON object on every row matching the filter.
+    ///
+    /// Returns the number of updated rows.
+    pub fn update(
+        &mut self,
+        table_name: &str,
+        filter: &str,
+        json: &str,
+    ) -> Result<u32, TableError> {
+        let filter = parse_filter(filter)?;
+        let patch = json::parse(json).map_err(|_| TableError::InvalidJson)?;
+        if !patch.is_object() {
+            return Err(TableError::SchemaMismatch);
+        }
+        let rows = match self.tables.get_mut(table_name) {
+            Some(rows) => rows,
+            None => return Ok(0),
+        };
+
+        let mut affected = 0;
+        for row in rows.iter_mut().filter(|row| filter.matches(row)) {
+            for (key, value) in patch.entries() {
+                row[key] = value.clone();
+            }
+            affected += 1;
+        }
+        Ok(affected)
+    }
+
+    /// Delete every row matching the filter.
+    ///
+    /// Returns the number of deleted rows.
+    pub fn delete(&mut self, table_name: &str, filter: &str) -> Result<u32, TableError> {
+        let filter = parse_filter(filter)?;
+        let rows = match self.tables.get_mut(table_name) {
+            Some(rows) => rows,
+            None => return Ok(0),
+        };
+
+        let before = rows.len();
+        rows.retain(|row| !filter.matches(row));
+        Ok((before - rows.len()) as u32)
+    }
+
+    /// Evaluate a SELECT query.
+    ///
+    /// Selecting from a table that does not exist returns an empty result.
+    pub fn query(&self, query: &str) -> Result<ResultSet, ReadError> {
+        let select = Parser::new(query)?.select()?;
+        let mut rows: Vec<&JsonValue> = self
+            .rows(&select.table)
+            .iter()
+            .filter(|row| match select.filter {
+                Some(ref filter) => filter.matches(row),
+                None => true,
+            })
+            .collect();
+
+        if !select.order.is_empty() {
+            rows.sort_by(|a, b| {
+                for &(ref column, descending) in &select.order {
+                    let ordering = order(&value(a, column), &value(b, column));
+                    if ordering != Ordering::Equal {
+                        return if descending {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        };
+                    }
+                }
+                Ordering::Equal
+            });
+        }
+
+        if let Some(limit) = select.limit {
+            rows.truncate(limit);
+        }
+
+        // Select every column in the order it first appears
+        let columns = match select.columns {
+            Some(columns) => columns,
+            None => {
+                let mut columns: Vec<String> = Vec::new();
+                for row in &rows {
+                    for (key, _) in row.entries() {
+                        if !columns.iter().any(|c| c == key) {
+                            columns.push(key.to_string());
+                        }
+                    }
+                }
+                columns
+            }
+        };
+
+        let mut result = ResultSet::new(columns);
+        for row in rows {
+            let values = result
+                .columns
+                .iter()
+                .map(|column| field(row, column).dump())
+                .collect();
+            result.push_row(values);
+        }
+        Ok(result)
+    }
+
+    /// Evaluate a SELECT query and return a page of at most `page_size` rows
+    /// starting at the position encoded in `token`.
+    pub fn query_page(&self, query: &str, token: &[u8], page_size: u32) -> Result<Page, ReadError> {
+        paginate(self.query(query)?, token, page_size)
+    }
+}
+
+/// Split a page out of a full result.
+///
+/// Tokens are the big endian offset of the first row of the page, an empty
+/// token starts at the first row.
+pub(crate) fn paginate(
+    mut result: ResultSet,
+    token: &[u8],
+    page_size: u32,
+) -> Result<Page, ReadError> {
+    let offset = match token.len() {
+        0 => 0,
+        4 => u32::from_be_bytes([token[0], token[1], token[2], token[3]]) as usize,
+        _ => return Err(ReadError::InvalidToken),
+    };
+    let start = offset.min(result.rows.len());
+    let end = start
+        .saturating_add(page_size.max(1) as usize)
+        .min(result.rows.len());
+    let token = if end < result.rows.len() {
+        (end as u32).to_be_bytes().to_vec()
+    } else {
+        Vec::new()
+    };
+    result.rows = result.rows.drain(start..end).collect();
+    Ok(Page { result, token })
+}
+
+struct Select {
+    columns: Option<Vec<String>>,
+    table: String,
+    filter: Option<Expr>,
+    order: Vec<(String, bool)>,
+    limit: Option<usize>,
+}
+
+enum Expr {
+    Compare(String, Comparison, Value),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, row: &JsonValue) -> bool {
+        match self {
+            Expr::Compare(column, comparison, literal) => {
+                let ordering = compare(&value(row, column), literal);
+                match comparison {
+                    Comparison::Eq => ordering == Some(Ordering::Equal),
+                    Comparison::Ne => ordering != Some(Ordering::Equal),
+                    Comparison::Lt => ordering == Some(Ordering::Less),
+                    Comparison::Le => {
+                        ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal)
+                    }
+                    Comparison::Gt => ordering == Some(Ordering::Greater),
+                    Comparison::Ge => {
+                        ordering == Some(Ordering::Greater) || ordering == Some(Ordering::Equal)
+                    }
+                }
+            }
+            Expr::And(left, right) => left.matches(row) && right.matches(row),
+            Expr::Or(left, right) => left.matches(row) || right.matches(row),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Str(String),
+}
+
+// Look up a possibly nested field of a row
+fn field<'a>(row: &'a JsonValue, column: &str) -> &'a JsonValue {
+    column.split('.').fold(row, |val, key| &val[key])
+}
+
+fn value(row: &JsonValue, column: &str) -> Value {
+    let val = field(row, column);
+    match val {
+        JsonValue::Null => Value::Null,
+        JsonValue::Boolean(val) => Value::Bool(*val),
+        JsonValue::Number(_) => match (val.as_i64(), val.as_u64()) {
+            (Some(val), _) => Value::Int(i128::from(val)),
+            (_, Some(val)) => Value::Int(i128::from(val)),
+            _ => Value::Str(val.dump()),
+        },
+        JsonValue::String(_) | JsonValue::Short(_) => Value::Str(val.to_string()),
+        _ => Value::Str(val.dump()),
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Int(b)) => a.parse::<i128>().ok().map(|a| a.cmp(b)),
+        (Value::Int(a), Value::Str(b)) => b.parse::<i128>().ok().map(|b| a.cmp(&b)),
+        _ => None,
+    }
+}
+
+// Total order used for sorting: null, booleans, integers and strings holding
+// integers by value, then other strings
+fn order(a: &Value, b: &Value) -> Ordering {
+    fn key(val: &Value) -> (u8, i128, &str) {
+        match val {
+            Value::Null => (0, 0, ""),
+            Value::Bool(val) => (1, i128::from(*val), ""),
+            Value::Int(val) => (2, *val, ""),
+            Value::Str(val) => match val.parse::<i128>() {
+                Ok(val) => (2, val, ""),
+                Err(_) => (3, 0, val),
+            },
+        }
+    }
+    key(a).cmp(&key(b))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i128),
+    Symbol(&'static str),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+        } else if c == '\'' {
+            // Quoted string, '' is an escaped quote
+            let mut val = String::new();
+            pos += 1;
+            loop {
+                match chars.get(pos) {
+                    Some('\'') if chars.get(pos + 1) == Some(&'\'') => {
+                        val.push('\'');
+                        pos += 2;
+                    }
+                    Some('\'') => {
+                        pos += 1;
+                        break;
+                    }
+                    Some(c) => {
+                        val.push(*c);
+                        pos += 1;
+                    }
+                    None => return Err(ParseError),
+                }
+            }
+            tokens.push(Token::Str(val));
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(pos + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let start = pos;
+            pos += 1;
+            while pos < chars.len() && chars[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let literal: String = chars[start..pos].iter().collect();
+            let val = literal.parse().map_err(|_| ParseError)?;
+            tokens.push(Token::Int(val));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = pos;
+            while pos < chars.len()
+                && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_' || chars[pos] == '.')
+            {
+                pos += 1;
+            }
+            tokens.push(Token::Ident(chars[start..pos].iter().collect()));
+        } else {
+            let next = chars.get(pos + 1).cloned();
+            let (symbol, len) = match (c, next) {
+                ('!', Some('=')) => ("!=", 2),
+                ('<', Some('>')) => ("!=", 2),
+                ('<', Some('=')) => ("<=", 2),
+                ('>', Some('=')) => (">=", 2),
+                ('<', _) => ("<", 1),
+                ('>', _) => (">", 1),
+                ('=', _) => ("=", 1),
+                ('(', _) => ("(", 1),
+                (')', _) => (")", 1),
+                (',', _) => (",", 1),
+                ('*', _) => ("*", 1),
+                _ => return Err(ParseError),
+            };
+            tokens.push(Token::Symbol(symbol));
+            pos += len;
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_filter(filter: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(filter)?;
+    let expr = parser.expr()?;
+    parser.end()?;
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, ParseError> {
+        Ok(Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) => ident.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.is_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(ParseError)
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            _ => Err(ParseError),
+        }
+    }
+
+    fn end(&self) -> Result<(), ParseError> {
+        if self.pos == self.tokens.len() {
+            Ok(())
+        } else {
+            Err(ParseError)
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, ParseError> {
+        self.keyword("SELECT")?;
+        let columns = if self.symbol("*") {
+            None
+        } else {
+            let mut columns = vec![self.ident()?];
+            while self.symbol(",") {
+                columns.push(self.ident()?);
+            }
+            Some(columns)
+        };
+
+        self.keyword("FROM")?;
+        let table = self.ident()?;
+
+        let filter = if self.is_keyword("WHERE") {
+            self.pos += 1;
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        let mut order = Vec::new();
+        if self.is_keyword("ORDER") {
+            self.pos += 1;
+            self.keyword("BY")?;
+            loop {
+                let column = self.ident()?;
+                let descending = if self.is_keyword("DESC") {
+                    self.pos += 1;
+                    true
+                } else {
+                    if self.is_keyword("ASC") {
+                        self.pos += 1;
+                    }
+                    false
+                };
+                order.push((column, descending));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.is_keyword("LIMIT") {
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Int(limit)) => Some(usize::try_from(limit).map_err(|_| ParseError)?),
+                _ => return Err(ParseError),
+            }
+        } else {
+            None
+        };
+
+        self.end()?;
+        Ok(Select {
+            columns,
+            table,
+            filter,
+            order,
+            limit,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and_expr()?;
+        while self.is_keyword("OR") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+        while self.is_keyword("AND") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.symbol("(") {
+            let expr = self.expr()?;
+            if !self.symbol(")") {
+                return Err(ParseError);
+            }
+            return Ok(expr);
+        }
+
+        let column = self.ident()?;
+        let comparison = match self.next() {
+            Some(Token::Symbol("=")) => Comparison::Eq,
+            Some(Token::Symbol("!=")) => Comparison::Ne,
+            Some(Token::Symbol("<")) => Comparison::Lt,
+            Some(Token::Symbol("<=")) => Comparison::Le,
+            Some(Token::Symbol(">")) => Comparison::Gt,
+            Some(Token::Symbol(">=")) => Comparison::Ge,
+            _ => return Err(ParseError),
+        };
+        let literal = match self.next() {
+            Some(Token::Str(val)) => Value::Str(val),
+            Some(Token::Int(val)) => Value::Int(val),
+            Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("TRUE") => {
+                Value::Bool(true)
+            }
+            Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("FALSE") => {
+                Value::Bool(false)
+            }
+            Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("NULL") => Value::Null,
+            _ => return Err(ParseError),
+        };
+        Ok(Expr::Compare(column, comparison, literal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Filter, Order, Query};
+    use super::*;
+
+    fn engine() -> MemoryEngine {
+        let mut engine = MemoryEngine::new();
+        engine
+            .insert("users", r#"{"name":"bob","age":"30","admin":false}"#)
+            .unwrap();
+        engine
+            .insert("users", r#"{"name":"alice","age":"41","admin":true}"#)
+            .unwrap();
+        engine
+            .insert(
+                "users",
+                r#"{"name":"o'neil","age":"19","admin":false,"address":{"city":"paris"}}"#,
+            )
+            .unwrap();
+        engine
+    }
+
+    fn names(result: &ResultSet) -> Vec<String> {
+        result
+            .rows()
+            .map(|row| row.get::<String>("name").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_all() {
+        let result = engine().query("SELECT * FROM users").unwrap();
+        assert_eq!(vec!["name", "age", "admin", "address"], result.columns);
+        assert_eq!(vec!["bob", "alice", "o'neil"], names(&result));
+        assert_eq!(Ok(30u64), result.row(0).unwrap().get("age"));
+        assert_eq!("null", result.rows[0].values[3]);
+    }
+
+    #[test]
+    fn test_builder_query() {
+        let query = Query::select(&["name"])
+            .from("users")
+            .filter(Filter::ge("age", 20).or(Filter::eq("name", "o'neil")))
+            .order_by("age", Order::Desc)
+            .limit(2)
+            .render()
+            .unwrap();
+        let result = engine().query(&query).unwrap();
+        assert_eq!(vec!["name"], result.columns);
+        assert_eq!(vec!["alice", "bob"], names(&result));
+    }
+
+    #[test]
+    fn test_where() {
+        let engine = engine();
+        let query = |q: &str| names(&engine.query(q).unwrap());
+        assert_eq!(
+            vec!["alice"],
+            query("SELECT * FROM users WHERE admin = TRUE")
+        );
+        assert_eq!(
+            vec!["o'neil"],
+            query("SELECT * FROM users WHERE name = 'o''neil'")
+        );
+        assert_eq!(
+            vec!["o'neil"],
+            query("SELECT * FROM users WHERE address.city = 'paris'")
+        );
+        assert_eq!(
+            vec!["bob", "o'neil"],
+            query("SELECT * FROM users WHERE age < 35 AND (admin = FALSE OR name = 'x')")
+        );
+        assert_eq!(
+            vec!["alice", "bob"],
+            query("SELECT * FROM users WHERE name <> 'o''neil' ORDER BY name")
+        );
+        assert!(query("SELECT * FROM missing").is_empty());
+    }
+
+    #[test]
+    fn test_order_mixed_values() {
+        let mut engine = MemoryEngine::new();
+        for value in &[
+            "20", r#""100""#, r#""3""#, r#""b""#, "null", r#""a""#, "true",
+        ] {
+            engine
+                .insert("values", &format!(r#"{{"value":{}}}"#, value))
+                .unwrap();
+        }
+        let result = engine
+            .query("SELECT value FROM values ORDER BY value")
+            .unwrap();
+        let values: Vec<&str> = result
+            .rows
+            .iter()
+            .map(|row| row.values[0].as_str())
+            .collect();
+        assert_eq!(
+            vec!["null", "true", r#""3""#, "20", r#""100""#, r#""a""#, r#""b""#],
+            values
+        );
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let engine = engine();
+        assert_eq!(
+            Err(ReadError::InvalidQuery),
+            engine.query("SELECT FROM users")
+        );
+        assert!(engine
+            .query("SELECT * FROM users WHERE name = 'bob")
+            .is_err());
+        assert!(engine.query("SELECT * FROM users LIMIT").is_err());
+        assert!(engine.query("SELECT * FROM users LIMIT -1").is_err());
+        assert!(engine
+            .query("SELECT * FROM users LIMIT 18446744073709551616")
+            .is_err());
+        assert!(engine.query("SELECT * FROM users extra").is_err());
+    }
+
+    #[test]
+    fn test_update_delete() {
+        let mut engine = engine();
+        assert_eq!(
+            Ok(2),
+            engine.update("users", "admin = FALSE", r#"{"admin":true}"#)
+        );
+        assert_eq!(
+            3,
+            names(
+                &engine
+                    .query("SELECT * FROM users WHERE admin = TRUE")
+                    .unwrap()
+            )
+            .len()
+        );
+        assert_eq!(Ok(1), engine.delete("users", "age > 40"));
+        assert_eq!(
+            vec!["bob", "o'neil"],
+            names(&engine.query("SELECT * FROM users").unwrap())
+        );
+        assert_eq!(Ok(0), engine.delete("missing", "age > 40"));
+        assert_eq!(Ok(0), engine.update("missing", "age > 40", "{}"));
+        assert_eq!(
+            Err(TableError::InvalidFilter),
+            engine.delete("users", "age >")
+        );
+        assert_eq!(Err(TableError::InvalidJson), engine.insert("users", "{"));
+        assert_eq!(
+            Err(TableError::SchemaMismatch),
+            engine.insert("users", "[1]")
+        );
+    }
+
+    #[test]
+    fn test_query_page() {
+        let engine = engine();
+        let query = "SELECT * FROM users ORDER BY name";
+
+        let page = engine.query_page(query, &[], 2).unwrap();
+        assert_eq!(vec!["alice", "bob"], names(&page.result));
+        assert!(!page.is_last());
+
+        let page = engine.query_page(query, &page.token, 2).unwrap();
+        assert_eq!(vec!["o'neil"], names(&page.result));
+        assert!(page.is_last());
+
+        assert_eq!(
+            Err(ReadError::InvalidToken),
+            engine.query_page(query, &[1], 2)
+        );
+    }
+}
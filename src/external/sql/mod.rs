@@ -0,0 +1,707 @@
+//! Access to the query host functions
+
+mod query;
+pub use self::query::{Comparison, Filter, Order, Param, Query, QueryError};
+
+mod rows;
+pub use self::rows::{FromRow, ResultSet, Row, RowValues, SqlError};
+
+mod table;
+use self::table::{check_columns, check_table};
+pub use self::table::{Column, JsonObject, Table, WriteError};
+
+mod cursor;
+pub use self::cursor::{Cursor, Page, DEFAULT_PAGE_SIZE};
+
+mod engine;
+pub(crate) use self::engine::paginate;
+pub use self::engine::{MemoryEngine, ReadError, TableError};
+
+use host::with_host;
+
+#[cfg(not(feature = "host-mock"))]
+use super::externs::{
+    _kq_delete, _kq_json_insert, _kq_json_update, _kq_query_fetch, _kq_query_page_run,
+    _kq_query_run,
+};
+
+#[cfg(feature = "host-mock")]
+use super::mock::{self, HostCall};
+
+// The mock tables reject a write with a code like a host
+#[cfg(feature = "host-mock")]
+fn rejected(_: TableError) -> WriteError {
+    WriteError::from_code(WRITE_ERROR)
+}
+
+#[cfg(feature = "host-mock")]
+fn with_tables<R, F: FnOnce(&mut MemoryEngine) -> R>(f: F) -> R {
+    mock::with(|mock| f(&mut mock.tables))
+}
+
+/// Execute a string query against the Mazzaroth leger.
+///
+/// # Arguments
+///
+/// * `query` - String query to be executed against the kvquery prefix
+///
+/// # Returns
+///
+///  Option<Vec<u8>>
+///  * `Some(Vec<u8>)` - result of the query execution, as returned by the host
+///  * None - the query resulted in no results
+pub fn exec(query: String) -> Option<Vec<u8>> {
+    with_host(|host| host.query_bytes(&query))
+}
+
+// Fetch the XDR encoded result of a query from the host.
+#[cfg(not(feature = "host-mock"))]
+fn fetch(query: String) -> Option<Vec<u8>> {
+    let query_bytes: Vec<u8> = query.as_bytes().to_vec();
+    let mut hash = [0u8; 16]; // 16 byte hash written by the host
+    let len = unsafe { _kq_query_run(query_bytes.as_ptr(), query_bytes.len(), hash.as_mut_ptr()) };
+    if len == 0 {
+        return None;
+    }
+    let mut result = vec![0u8; len as usize];
+    unsafe { _kq_query_fetch(result.as_mut_ptr(), hash.as_ptr()) };
+    Some(result)
+}
+
+// Execute a query, `Ok(None)` if it resulted in no results.
+//
+// The host reports no results and failures alike with a zero length, only
+// the mock tables can tell an invalid query apart.
+#[cfg(not(feature = "host-mock"))]
+fn run(query: String) -> Result<Option<Vec<u8>>, SqlError> {
+    Ok(fetch(query))
+}
+
+#[cfg(feature = "host-mock")]
+fn run(query: String) -> Result<Option<Vec<u8>>, SqlError> {
+    if let Some(result) = mock::with(|mock| mock.query_result.clone()) {
+        return Ok(Some(result));
+    }
+    let result = with_tables(|tables| tables.query(&query)).map_err(|_| SqlError::Host)?;
+    Ok(Some(result.to_xdr()?))
+}
+
+// Fetch a single XDR encoded `Page` of a query result from the host.
+#[cfg(not(feature = "host-mock"))]
+fn exec_page(query: &str, token: &[u8], page_size: u32) -> Option<Vec<u8>> {
+    let mut hash = [0u8; 16]; // 16 byte hash written by the host
+    let len = unsafe {
+        _kq_query_page_run(
+            query.as_ptr(),
+            query.len(),
+            token.as_ptr(),
+            token.len(),
+            page_size,
+            hash.as_mut_ptr(),
+        )
+    };
+    if len == 0 {
+        return None;
+    }
+    let mut result = vec![0u8; len as usize];
+    unsafe { _kq_query_fetch(result.as_mut_ptr(), hash.as_ptr()) };
+    Some(result)
+}
+
+// Execute a query on the host and return the result bytes unchanged, used by `HostImpl`.
+pub(crate) fn raw_query_bytes(query: &str) -> Option<Vec<u8>> {
+    run(query.to_string()).unwrap_or(None)
+}
+
+// Execute a query on the host and decode the result, used by `HostImpl`.
+pub(crate) fn raw_query(query: &str) -> Result<ResultSet, SqlError> {
+    match run(query.to_string())? {
+        Some(bytes) => ResultSet::from_xdr(&bytes),
+        None => Ok(ResultSet::default()),
+    }
+}
+
+// Execute a query on the host and decode a page of the result, used by `HostImpl`.
+pub(crate) fn raw_query_page(query: &str, token: &[u8], page_size: u32) -> Result<Page, SqlError> {
+    match run_page(query, token, page_size)? {
+        Some(bytes) => Page::from_xdr(&bytes),
+        None => Ok(Page::default()),
+    }
+}
+
+// Fetch a page of a query, `Ok(None)` if it resulted in no results.
+#[cfg(not(feature = "host-mock"))]
+fn run_page(query: &str, token: &[u8], page_size: u32) -> Result<Option<Vec<u8>>, SqlError> {
+    Ok(exec_page(query, token, page_size))
+}
+
+#[cfg(feature = "host-mock")]
+fn run_page(query: &str, token: &[u8], page_size: u32) -> Result<Option<Vec<u8>>, SqlError> {
+    let result = match mock::with(|mock| mock.query_result.clone()) {
+        Some(bytes) => ResultSet::from_xdr(&bytes)?,
+        None => with_tables(|tables| tables.query(query)).map_err(|_| SqlError::Host)?,
+    };
+    let page = engine::paginate(result, token, page_size).map_err(|_| SqlError::Host)?;
+    Ok(Some(page.to_xdr()?))
+}
+
+/// Render a query built with `Query` and execute it against the Mazzaroth ledger.
+///
+/// # Arguments
+///
+/// * `query` - The query to render and execute
+///
+/// # Returns
+///
+///  Result<Option<Vec<u8>>, QueryError>
+///  * `Ok(Some(Vec<u8>))` - xdr encoded result of the query execution
+///  * `Ok(None)` - the query resulted in no results
+///  * `Err(QueryError)` - the query could not be rendered
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Filter, Query};
+/// let query = Query::select(&["*"]).from("users").filter(Filter::eq("name", name));
+/// let result = sql::exec_query(&query)?;
+/// ```
+pub fn exec_query(query: &Query) -> Result<Option<Vec<u8>>, QueryError> {
+    Ok(exec(query.render()?))
+}
+
+/// Execute a string query and decode the result into rows.
+///
+/// # Arguments
+///
+/// * `query` - String query to be executed against the kvquery prefix
+///
+/// # Returns
+///
+///  Result<ResultSet, SqlError>
+///  * `Ok(ResultSet)` - rows returned by the query, empty if no rows matched
+///  * `Err(SqlError)` - the host failed to execute the query or the result could not be decoded
+pub fn query_raw(query: String) -> Result<ResultSet, SqlError> {
+    with_host(|host| host.query(&query))
+}
+
+/// Execute a query built with `Query` and decode the result into rows.
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Query};
+/// let result = sql::query(&Query::select(&["*"]).from("users"))?;
+/// for row in result.rows() {
+///     let name: String = row.get("name")?;
+/// }
+/// ```
+pub fn query(query: &Query) -> Result<ResultSet, SqlError> {
+    query_raw(query.render()?)
+}
+
+/// Execute a query built with `Query` and map every row into `T`.
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Filter, Query};
+/// use mazzaroth_rs_derive::FromRow;
+///
+/// #[derive(FromRow)]
+/// struct User {
+///     name: String,
+///     age: u64,
+/// }
+///
+/// let users: Vec<User> = sql::select(&Query::select(&["name", "age"]).from("users"))?;
+/// ```
+pub fn select<T: FromRow>(query: &Query) -> Result<Vec<T>, SqlError> {
+    self::query(query)?.decode()
+}
+
+/// Set a fixed result returned by the mock query functions.
+///
+/// `None` evaluates queries against the mock tables again.
+#[cfg(feature = "host-mock")]
+pub fn set_query_result(result: Option<&ResultSet>) {
+    let bytes = result.map(|result| result.to_xdr().unwrap());
+    mock::with(|mock| mock.query_result = bytes);
+}
+
+/// Executes a query that will insert a JSON object into the specified table.
+///
+/// # Arguments
+///
+/// * `table_name` - String name of the table to insert into
+/// * `json` - String JSON string to insert into the table
+///
+/// # Returns
+///
+///  Result<u32, u32>
+///  * Ok(x) - resulting return code upon success
+///  * Err(x) - resulting return code upon error
+pub fn insert(table_name: String, json: String) -> Result<u32, u32> {
+    with_host(|host| host.insert(&table_name, &json))
+        .map(|_| 0)
+        .map_err(|err| match err {
+            WriteError::Unknown(code) => code,
+            _ => WRITE_ERROR,
+        })
+}
+
+/// Insert a JSON object into a table.
+///
+/// # Arguments
+///
+/// * `table_name` - Name of the table to insert into
+/// * `json` - JSON object to insert as a row
+///
+/// # Returns
+///
+///  Result<(), WriteError>
+///  * `Ok(())` - the row was inserted
+///  * `Err(WriteError)` - the host rejected the insert
+pub fn insert_json(table_name: &str, json: &str) -> Result<(), WriteError> {
+    check_table(table_name)?;
+    with_host(|host| host.insert(table_name, json))
+}
+
+// Code of a write rejected without a host code, returned by `insert` and by
+// the mock tables like the runner does
+const WRITE_ERROR: u32 = 1;
+
+// Insert a row on the host, used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_insert(table_name: &str, json: &str) -> Result<(), WriteError> {
+    match unsafe {
+        _kq_json_insert(
+            table_name.as_ptr(),
+            table_name.len(),
+            json.as_ptr(),
+            json.len(),
+        )
+    } {
+        0 => Ok(()),
+        x => Err(WriteError::from_code(x)),
+    }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_insert(table_name: &str, json: &str) -> Result<(), WriteError> {
+    mock::record(HostCall::Insert {
+        table: table_name.to_string(),
+        json: json.to_string(),
+    });
+    mock::with(|mock| mock.insert_result).map_err(WriteError::from_code)?;
+    with_tables(|tables| tables.insert(table_name, json)).map_err(rejected)
+}
+
+/// Insert a typed row into the table declared by its `Table` implementation.
+///
+/// # Arguments
+///
+/// * `row` - The row to serialize and insert
+///
+/// # Returns
+///
+///  Result<(), WriteError>
+///  * `Ok(())` - the row was inserted
+///  * `Err(WriteError)` - the row could not be serialized, a column name is not a
+///    valid identifier or the host rejected the insert
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql;
+/// use mazzaroth_rs_derive::KqTable;
+///
+/// #[derive(KqTable)]
+/// #[table = "users"]
+/// struct User {
+///     name: String,
+///     age: u64,
+/// }
+///
+/// sql::insert_row(&User { name: "bob".to_string(), age: 30 })?;
+/// ```
+pub fn insert_row<T: Table>(row: &T) -> Result<(), WriteError> {
+    check_columns::<T>()?;
+    insert_json(T::table_name(), &row.to_json()?)
+}
+
+/// Update the rows of a table matching a filter with the fields of a JSON object.
+///
+/// Fields not present in the JSON object are left unchanged.
+///
+/// # Arguments
+///
+/// * `table_name` - Name of the table to update
+/// * `filter` - Filter selecting the rows to update
+/// * `json` - JSON object with the fields to set
+///
+/// # Returns
+///
+///  Result<u32, WriteError>
+///  * `Ok(u32)` - the number of updated rows
+///  * `Err(WriteError)` - the filter could not be rendered or the host rejected the update
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Filter};
+/// let updated = sql::update_json("users", &Filter::eq("name", "bob"), r#"{"age":"31"}"#.to_string())?;
+/// ```
+pub fn update_json(table_name: &str, filter: &Filter, json: String) -> Result<u32, WriteError> {
+    check_table(table_name)?;
+    let mut condition = String::new();
+    filter.render(&mut condition)?;
+    with_host(|host| host.update(table_name, &condition, &json))
+}
+
+/// Update the rows of a table matching a filter with the fields of a typed patch.
+///
+/// The patch is a `Table` struct declaring the table and only the columns to set.
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Filter};
+/// use mazzaroth_rs_derive::KqTable;
+///
+/// #[derive(KqTable)]
+/// #[table = "users"]
+/// struct AgePatch {
+///     age: u64,
+/// }
+///
+/// let updated = sql::update(&Filter::eq("name", "bob"), &AgePatch { age: 31 })?;
+/// ```
+pub fn update<T: Table>(filter: &Filter, patch: &T) -> Result<u32, WriteError> {
+    check_columns::<T>()?;
+    update_json(T::table_name(), filter, patch.to_json()?)
+}
+
+// Update rows on the host, used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_update(table_name: &str, filter: &str, json: &str) -> Result<u32, WriteError> {
+    let mut affected: u32 = 0;
+    match unsafe {
+        _kq_json_update(
+            table_name.as_ptr(),
+            table_name.len(),
+            filter.as_ptr(),
+            filter.len(),
+            json.as_ptr(),
+            json.len(),
+            &mut affected,
+        )
+    } {
+        0 => Ok(affected),
+        x => Err(WriteError::from_code(x)),
+    }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_update(table_name: &str, filter: &str, json: &str) -> Result<u32, WriteError> {
+    with_tables(|tables| tables.update(table_name, filter, json)).map_err(rejected)
+}
+
+/// Delete the rows of a table matching a filter.
+///
+/// # Arguments
+///
+/// * `table_name` - Name of the table to delete from
+/// * `filter` - Filter selecting the rows to delete
+///
+/// # Returns
+///
+///  Result<u32, WriteError>
+///  * `Ok(u32)` - the number of deleted rows
+///  * `Err(WriteError)` - the filter could not be rendered or the host rejected the delete
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::external::sql::{self, Filter};
+/// let deleted = sql::delete("users", &Filter::lt("age", 18))?;
+/// ```
+pub fn delete(table_name: &str, filter: &Filter) -> Result<u32, WriteError> {
+    check_table(table_name)?;
+    let mut condition = String::new();
+    filter.render(&mut condition)?;
+    with_host(|host| host.delete_rows(table_name, &condition))
+}
+
+// Delete rows on the host, used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_delete(table_name: &str, filter: &str) -> Result<u32, WriteError> {
+    let mut affected: u32 = 0;
+    match unsafe {
+        _kq_delete(
+            table_name.as_ptr(),
+            table_name.len(),
+            filter.as_ptr(),
+            filter.len(),
+            &mut affected,
+        )
+    } {
+        0 => Ok(affected),
+        x => Err(WriteError::from_code(x)),
+    }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_delete(table_name: &str, filter: &str) -> Result<u32, WriteError> {
+    with_tables(|tables| tables.delete(table_name, filter)).map_err(rejected)
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+
+    struct User {
+        name: String,
+        age: u64,
+    }
+
+    impl Table for User {
+        fn table_name() -> &'static str {
+            "mock_users"
+        }
+
+        fn columns() -> Vec<Column> {
+            vec![
+                Column {
+                    name: "name",
+                    column_type: "string",
+                },
+                Column {
+                    name: "age",
+                    column_type: "uint64",
+                },
+            ]
+        }
+
+        fn to_json(&self) -> Result<String, WriteError> {
+            let mut object = JsonObject::new();
+            object.field("name", &self.name)?;
+            object.field("age", &self.age)?;
+            Ok(object.finish())
+        }
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &Row) -> Result<Self, SqlError> {
+            Ok(User {
+                name: row.get("name")?,
+                age: row.get("age")?,
+            })
+        }
+    }
+
+    struct AgePatch {
+        age: u64,
+    }
+
+    impl Table for AgePatch {
+        fn table_name() -> &'static str {
+            "mock_users"
+        }
+
+        fn columns() -> Vec<Column> {
+            vec![Column {
+                name: "age",
+                column_type: "uint64",
+            }]
+        }
+
+        fn to_json(&self) -> Result<String, WriteError> {
+            let mut object = JsonObject::new();
+            object.field("age", &self.age)?;
+            Ok(object.finish())
+        }
+    }
+
+    fn user(name: &str, age: u64) -> User {
+        User {
+            name: name.to_string(),
+            age,
+        }
+    }
+
+    #[test]
+    fn test_query_rows() {
+        let mut result = ResultSet::new(vec!["name".to_string()]);
+        result.push_row(vec![r#""bob""#.to_string()]);
+        mock::with(|mock| mock.query_result = Some(result.to_xdr().unwrap()));
+        let rows = query(&Query::select(&["name"]).from("users")).unwrap();
+        assert_eq!(Ok("bob".to_string()), rows.row(0).unwrap().get("name"));
+
+        let empty = ResultSet::new(vec!["name".to_string()]);
+        mock::with(|mock| mock.query_result = Some(empty.to_xdr().unwrap()));
+        assert!(query_raw("SELECT * FROM users".to_string())
+            .unwrap()
+            .is_empty());
+
+        mock::with(|mock| mock.query_result = None);
+        assert_eq!(
+            Err(SqlError::Host),
+            query_raw("SELECT FROM users".to_string())
+        );
+    }
+
+    #[test]
+    fn test_exec_bytes() {
+        // bytes returned by the host are passed through, whatever their encoding
+        mock::with(|mock| mock.query_result = Some(vec![1, 2, 3]));
+        assert_eq!(Some(vec![1, 2, 3]), exec("SELECT * FROM users".to_string()));
+        assert!(query_raw("SELECT * FROM users".to_string()).is_err());
+        mock::with(|mock| mock.query_result = None);
+    }
+
+    #[test]
+    fn test_insert_row() {
+        assert_eq!(Ok(()), insert_row(&user("bob", 30)));
+        mock::assert_called(&HostCall::Insert {
+            table: "mock_users".to_string(),
+            json: r#"{"name":"bob","age":"30"}"#.to_string(),
+        });
+        mock::with(|mock| mock.insert_result = Err(3));
+        assert_eq!(Err(WriteError::Unknown(3)), insert_row(&user("bob", 30)));
+    }
+
+    #[test]
+    fn test_update() {
+        insert_row(&user("bob", 30)).unwrap();
+        insert_row(&user("bob", 31)).unwrap();
+        assert_eq!(
+            Ok(2),
+            update(&Filter::eq("name", "bob"), &AgePatch { age: 40 })
+        );
+        assert_eq!(
+            Ok(0),
+            update_json("users", &Filter::eq("name", "bob"), "{}".to_string())
+        );
+        assert_eq!(
+            Err(WriteError::Query(QueryError::InvalidIdentifier(
+                "bad name".to_string()
+            ))),
+            update(&Filter::eq("bad name", "bob"), &AgePatch { age: 40 })
+        );
+        // the mock tables reject a write with a code like a host
+        assert_eq!(
+            Err(WriteError::Unknown(WRITE_ERROR)),
+            update_json("mock_users", &Filter::eq("name", "bob"), "[1]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_delete() {
+        for age in &[12, 17, 30] {
+            insert_row(&user("bob", *age)).unwrap();
+        }
+        assert_eq!(Ok(2), delete("mock_users", &Filter::lt("age", 18)));
+    }
+
+    #[test]
+    fn test_mock_tables() {
+        insert_row(&user("bob", 30)).unwrap();
+        insert_row(&user("alice", 41)).unwrap();
+        insert_row(&user("carol", 17)).unwrap();
+
+        let adults: Vec<User> = select(
+            &Query::select(&["name", "age"])
+                .from("mock_users")
+                .filter(Filter::ge("age", 18))
+                .order_by("age", Order::Asc),
+        )
+        .unwrap();
+        let names: Vec<&str> = adults.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(vec!["bob", "alice"], names);
+
+        assert_eq!(
+            Ok(1),
+            update(&Filter::eq("name", "carol"), &AgePatch { age: 18 })
+        );
+        assert_eq!(Ok(1), delete("mock_users", &Filter::eq("name", "bob")));
+        assert_eq!(Ok(0), delete("missing", &Filter::eq("name", "bob")));
+
+        let users: Vec<User> = select(
+            &Query::select(&[])
+                .from("mock_users")
+                .order_by("name", Order::Asc),
+        )
+        .unwrap();
+        let ages: Vec<u64> = users.iter().map(|u| u.age).collect();
+        assert_eq!(vec![41, 18], ages);
+
+        assert!(query_raw("SELECT * FROM empty_users".to_string())
+            .unwrap()
+            .is_empty());
+        assert_eq!(Err(SqlError::Host), query_raw("SELECT FROM".to_string()));
+
+        // a fixed insert result overrides the mock tables
+        mock::with(|mock| mock.insert_result = Err(3));
+        assert_eq!(Err(WriteError::Unknown(3)), insert_row(&user("dave", 5)));
+        mock::with(|mock| mock.insert_result = Ok(0));
+        assert_eq!(
+            Err(WRITE_ERROR),
+            insert("mock_json".to_string(), "{".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_table_name() {
+        fn invalid<T>() -> Result<T, WriteError> {
+            Err(WriteError::Query(QueryError::InvalidIdentifier(
+                "users; DROP".to_string(),
+            )))
+        }
+        assert_eq!(invalid(), insert_json("users; DROP", "{}"));
+        assert_eq!(
+            invalid(),
+            update_json("users; DROP", &Filter::eq("name", "bob"), "{}".to_string())
+        );
+        assert_eq!(invalid(), delete("users; DROP", &Filter::eq("name", "bob")));
+        assert!(mock::calls().is_empty());
+    }
+
+    #[test]
+    fn test_cursor() {
+        for (i, name) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            let json = format!(r#"{{"name":"{}","age":"{}"}}"#, name, i);
+            insert("mock_pages".to_string(), json).unwrap();
+        }
+        let query = Query::select(&["name", "age"])
+            .from("mock_pages")
+            .order_by("name", Order::Asc);
+
+        let mut cursor = Cursor::new(&query).unwrap().page_size(2);
+        let page: Vec<User> = cursor.next_rows().unwrap().unwrap();
+        let names: Vec<&str> = page.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(vec!["a", "b"], names);
+
+        // resume in a new cursor from the saved token
+        let token = cursor.token().unwrap().to_vec();
+        let pages: Vec<usize> = Cursor::new(&query)
+            .unwrap()
+            .page_size(2)
+            .resume(token)
+            .map(|page| page.unwrap().len())
+            .collect();
+        assert_eq!(vec![2, 1], pages);
+
+        let mut cursor = Cursor::raw("SELECT * FROM mock_empty".to_string());
+        assert_eq!(Ok(None), cursor.next_page());
+        assert_eq!(None, cursor.token());
+
+        let mut cursor = Cursor::raw("SELECT * FROM mock_pages".to_string()).resume(vec![1]);
+        assert_eq!(Err(SqlError::Host), cursor.next_page());
+        assert_eq!(None, cursor.token());
+
+        let mut cursor = Cursor::raw("SELECT FROM".to_string());
+        assert_eq!(Some(Err(SqlError::Host)), cursor.next());
+        assert_eq!(None, cursor.next());
+    }
+}
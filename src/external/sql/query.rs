@@ -0,0 +1,525 @@
+//! Builder for kvquery query strings.
+//!
+//! Values are always bound as parameters and escaped when the query is
+//! rendered, so user supplied arguments can never change the structure of
+//! the query.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::external::sql::{self, Filter, Order, Query};
+//!
+//! let query = Query::select(&["name", "balance"])
+//!     .from("accounts")
+//!     .filter(Filter::eq("owner", sender_name).and(Filter::gt("balance", 100)))
+//!     .order_by("balance", Order::Desc)
+//!     .limit(10);
+//!
+//! // SELECT name, balance FROM accounts WHERE (owner = 'bob' AND balance > 100) ORDER BY balance DESC LIMIT 10
+//! let result = sql::exec_query(&query);
+//! ```
+
+use std::fmt;
+
+/// Defines the errors that can occur when rendering a query.
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    /// A table or column name contains characters that are not allowed in identifiers
+    InvalidIdentifier(String),
+    /// The query does not select from a table
+    MissingTable,
+    /// The number of bound parameters does not match the placeholders in a raw filter
+    ParameterCount { expected: usize, found: usize },
+    /// A raw filter closes a parenthesis it did not open or leaves one open
+    UnbalancedParentheses(String),
+    /// A raw filter opens a string literal it never closes
+    UnterminatedString(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::InvalidIdentifier(ident) => write!(f, "Invalid identifier: {}", ident),
+            QueryError::MissingTable => write!(f, "Query does not select from a table."),
+            QueryError::ParameterCount { expected, found } => write!(
+                f,
+                "Expected {} query parameters but found {}.",
+                expected, found
+            ),
+            QueryError::UnbalancedParentheses(condition) => {
+                write!(f, "Unbalanced parentheses in filter: {}", condition)
+            }
+            QueryError::UnterminatedString(condition) => {
+                write!(f, "Unterminated string in filter: {}", condition)
+            }
+        }
+    }
+}
+
+/// A value bound into a query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Param {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Str(String),
+}
+
+impl Param {
+    fn render(&self, out: &mut String) {
+        match self {
+            Param::Null => out.push_str("NULL"),
+            Param::Bool(val) => out.push_str(if *val { "TRUE" } else { "FALSE" }),
+            Param::Int(val) => out.push_str(&val.to_string()),
+            Param::UInt(val) => out.push_str(&val.to_string()),
+            Param::Str(val) => {
+                // Strings are single quoted, embedded quotes are doubled
+                out.push('\'');
+                for c in val.chars() {
+                    if c == '\'' {
+                        out.push('\'');
+                    }
+                    out.push(c);
+                }
+                out.push('\'');
+            }
+        }
+    }
+}
+
+impl From<bool> for Param {
+    fn from(val: bool) -> Self {
+        Param::Bool(val)
+    }
+}
+
+impl From<i32> for Param {
+    fn from(val: i32) -> Self {
+        Param::Int(i64::from(val))
+    }
+}
+
+impl From<i64> for Param {
+    fn from(val: i64) -> Self {
+        Param::Int(val)
+    }
+}
+
+impl From<u32> for Param {
+    fn from(val: u32) -> Self {
+        Param::UInt(u64::from(val))
+    }
+}
+
+impl From<u64> for Param {
+    fn from(val: u64) -> Self {
+        Param::UInt(val)
+    }
+}
+
+impl<'a> From<&'a str> for Param {
+    fn from(val: &'a str) -> Self {
+        Param::Str(val.to_string())
+    }
+}
+
+impl From<String> for Param {
+    fn from(val: String) -> Self {
+        Param::Str(val)
+    }
+}
+
+impl<T: Into<Param>> From<Option<T>> for Param {
+    fn from(val: Option<T>) -> Self {
+        match val {
+            Some(val) => val.into(),
+            None => Param::Null,
+        }
+    }
+}
+
+/// Comparison operators usable in a filter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn as_str(self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+/// A condition used in the WHERE clause of a query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Compare a column to a bound value
+    Compare(String, Comparison, Param),
+    /// Both filters must match
+    And(Box<Filter>, Box<Filter>),
+    /// Either filter must match
+    Or(Box<Filter>, Box<Filter>),
+    /// A raw condition where each `?` outside of a quoted string is replaced
+    /// by the next bound parameter
+    Raw(String, Vec<Param>),
+}
+
+impl Filter {
+    /// Column is equal to value
+    pub fn eq<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Eq, value.into())
+    }
+
+    /// Column is not equal to value
+    pub fn ne<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Ne, value.into())
+    }
+
+    /// Column is less than value
+    pub fn lt<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Lt, value.into())
+    }
+
+    /// Column is less than or equal to value
+    pub fn le<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Le, value.into())
+    }
+
+    /// Column is greater than value
+    pub fn gt<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Gt, value.into())
+    }
+
+    /// Column is greater than or equal to value
+    pub fn ge<P: Into<Param>>(column: &str, value: P) -> Self {
+        Filter::Compare(column.to_string(), Comparison::Ge, value.into())
+    }
+
+    /// Raw condition with `?` placeholders bound to params in order.
+    ///
+    /// The condition is rendered in parentheses, so combining it with other
+    /// filters never changes its meaning.
+    pub fn raw(condition: &str, params: Vec<Param>) -> Self {
+        Filter::Raw(condition.to_string(), params)
+    }
+
+    /// Combine with another filter that must also match
+    pub fn and(self, other: Filter) -> Self {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    /// Combine with another filter where either may match
+    pub fn or(self, other: Filter) -> Self {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Render the filter as a kvquery condition.
+    pub fn render(&self, out: &mut String) -> Result<(), QueryError> {
+        match self {
+            Filter::Compare(column, comparison, param) => {
+                push_identifier(out, column)?;
+                out.push(' ');
+                out.push_str(comparison.as_str());
+                out.push(' ');
+                param.render(out);
+            }
+            Filter::And(left, right) => render_pair(out, left, "AND", right)?,
+            Filter::Or(left, right) => render_pair(out, left, "OR", right)?,
+            Filter::Raw(condition, params) => render_raw(out, condition, params)?,
+        }
+        Ok(())
+    }
+}
+
+fn render_pair(
+    out: &mut String,
+    left: &Filter,
+    op: &str,
+    right: &Filter,
+) -> Result<(), QueryError> {
+    out.push('(');
+    left.render(out)?;
+    out.push(' ');
+    out.push_str(op);
+    out.push(' ');
+    right.render(out)?;
+    out.push(')');
+    Ok(())
+}
+
+fn render_raw(out: &mut String, condition: &str, params: &[Param]) -> Result<(), QueryError> {
+    let unbalanced = || QueryError::UnbalancedParentheses(condition.to_string());
+    let mut bound = params.iter();
+    let mut expected = 0;
+    let mut quoted = false;
+    let mut depth = 0usize;
+    out.push('(');
+    for c in condition.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                out.push(c);
+            }
+            '(' if !quoted => {
+                depth += 1;
+                out.push(c);
+            }
+            ')' if !quoted => {
+                depth = depth.checked_sub(1).ok_or_else(unbalanced)?;
+                out.push(c);
+            }
+            '?' if !quoted => {
+                expected += 1;
+                if let Some(param) = bound.next() {
+                    param.render(out);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    if quoted {
+        return Err(QueryError::UnterminatedString(condition.to_string()));
+    }
+    if depth != 0 {
+        return Err(unbalanced());
+    }
+    out.push(')');
+    if expected != params.len() {
+        return Err(QueryError::ParameterCount {
+            expected,
+            found: params.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Sort direction for ORDER BY.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// A SELECT query against a kvquery table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    columns: Vec<String>,
+    table: Option<String>,
+    filter: Option<Filter>,
+    order: Vec<(String, Order)>,
+    limit: Option<u64>,
+}
+
+impl Query {
+    /// Select the given columns, an empty slice or `"*"` selects every column.
+    pub fn select(columns: &[&str]) -> Self {
+        Query {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            ..Query::default()
+        }
+    }
+
+    /// Table to select from
+    pub fn from(mut self, table: &str) -> Self {
+        self.table = Some(table.to_string());
+        self
+    }
+
+    /// Filter rows, combined with any previous filter using AND
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Sort results by a column, may be called multiple times
+    pub fn order_by(mut self, column: &str, order: Order) -> Self {
+        self.order.push((column.to_string(), order));
+        self
+    }
+
+    /// Maximum number of rows to return
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Render the query as the string passed to the host.
+    pub fn render(&self) -> Result<String, QueryError> {
+        let table = self.table.as_ref().ok_or(QueryError::MissingTable)?;
+
+        let mut out = String::from("SELECT ");
+        if self.columns.is_empty() || self.columns.iter().any(|c| c == "*") {
+            out.push('*');
+        } else {
+            for (idx, column) in self.columns.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                push_identifier(&mut out, column)?;
+            }
+        }
+
+        out.push_str(" FROM ");
+        push_identifier(&mut out, table)?;
+
+        if let Some(ref filter) = self.filter {
+            out.push_str(" WHERE ");
+            filter.render(&mut out)?;
+        }
+
+        for (idx, (column, order)) in self.order.iter().enumerate() {
+            out.push_str(if idx == 0 { " ORDER BY " } else { ", " });
+            push_identifier(&mut out, column)?;
+            out.push_str(match order {
+                Order::Asc => " ASC",
+                Order::Desc => " DESC",
+            });
+        }
+
+        if let Some(limit) = self.limit {
+            out.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        Ok(out)
+    }
+}
+
+/// Identifiers may contain ascii letters, digits, underscores and dots for nested
+/// fields and must not start with a digit.
+pub(crate) fn push_identifier(out: &mut String, ident: &str) -> Result<(), QueryError> {
+    let valid = match ident.chars().next() {
+        Some(first) => {
+            !first.is_ascii_digit()
+                && ident
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(QueryError::InvalidIdentifier(ident.to_string()));
+    }
+    out.push_str(ident);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_all() {
+        assert_eq!(
+            Ok("SELECT * FROM users".to_string()),
+            Query::select(&[]).from("users").render()
+        );
+    }
+
+    #[test]
+    fn test_full_query() {
+        let query = Query::select(&["name", "age"])
+            .from("users")
+            .filter(Filter::eq("name", "bob"))
+            .filter(Filter::ge("age", 21).or(Filter::eq("admin", true)))
+            .order_by("age", Order::Desc)
+            .order_by("name", Order::Asc)
+            .limit(10);
+        assert_eq!(
+            Ok("SELECT name, age FROM users WHERE (name = 'bob' AND (age >= 21 OR admin = TRUE)) ORDER BY age DESC, name ASC LIMIT 10".to_string()),
+            query.render()
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let query = Query::select(&["*"])
+            .from("users")
+            .filter(Filter::eq("name", "x' OR '1' = '1"));
+        assert_eq!(
+            Ok("SELECT * FROM users WHERE name = 'x'' OR ''1'' = ''1'".to_string()),
+            query.render()
+        );
+    }
+
+    #[test]
+    fn test_raw_filter() {
+        let query = Query::select(&["*"]).from("users").filter(Filter::raw(
+            "age > ? AND note != '?' AND name = ?",
+            vec![30.into(), "it's".into()],
+        ));
+        assert_eq!(
+            Ok(
+                "SELECT * FROM users WHERE (age > 30 AND note != '?' AND name = 'it''s')"
+                    .to_string()
+            ),
+            query.render()
+        );
+
+        let query = Query::select(&["*"])
+            .from("users")
+            .filter(Filter::raw("age > ?", vec![]));
+        assert_eq!(
+            Err(QueryError::ParameterCount {
+                expected: 1,
+                found: 0
+            }),
+            query.render()
+        );
+    }
+
+    #[test]
+    fn test_raw_filter_precedence() {
+        let filter =
+            Filter::raw("a = ? OR b = ?", vec![1.into(), 2.into()]).and(Filter::eq("c", 1));
+        let mut condition = String::new();
+        filter.render(&mut condition).unwrap();
+        assert_eq!("((a = 1 OR b = 2) AND c = 1)", condition);
+
+        let filter = Filter::raw("a = 1) OR (b = ?", vec![2.into()]);
+        assert_eq!(
+            Err(QueryError::UnbalancedParentheses(
+                "a = 1) OR (b = ?".to_string()
+            )),
+            filter.render(&mut String::new())
+        );
+        let filter = Filter::raw("(a = ')' OR b = '(')", vec![]);
+        assert!(filter.render(&mut String::new()).is_ok());
+
+        let filter = Filter::raw("a = 'it's' AND b = ?", vec![2.into()]);
+        assert_eq!(
+            Err(QueryError::UnterminatedString(
+                "a = 'it's' AND b = ?".to_string()
+            )),
+            filter.render(&mut String::new())
+        );
+    }
+
+    #[test]
+    fn test_invalid_identifier() {
+        assert_eq!(Err(QueryError::MissingTable), Query::select(&[]).render());
+        assert_eq!(
+            Err(QueryError::InvalidIdentifier("users; DROP".to_string())),
+            Query::select(&[]).from("users; DROP").render()
+        );
+        assert_eq!(
+            Err(QueryError::InvalidIdentifier("1col".to_string())),
+            Query::select(&["1col"]).from("users").render()
+        );
+    }
+}
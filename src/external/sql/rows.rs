@@ -0,0 +1,247 @@
+//! Typed access to the results of kvquery queries.
+//!
+//! `ResultSet` is a result encoding of this library, not a type of
+//! `mazzaroth-xdr`: the XDR encoded column names followed by the rows. The
+//! mock host, `MemoryEngine` and the wasm runner return results in this
+//! encoding and the typed functions `query` and `select` decode it. `exec`
+//! passes the bytes returned by the host through unchanged.
+//!
+//! Every value in a row is the JSON encoding of the column value, the same
+//! encoding used for contract arguments, so it can be decoded into any type
+//! implementing `XDRIn`.
+
+use super::QueryError;
+
+use json;
+use std::fmt;
+use std::io::Write;
+use xdr_rs_serialize::de::{read_json_string, read_var_array, read_var_array_json, XDRIn};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{write_var_array, write_var_array_json, XDROut};
+
+/// Defines the errors that can be returned by the typed query functions.
+#[derive(Debug, PartialEq)]
+pub enum SqlError {
+    /// The query could not be built
+    Query(QueryError),
+    /// The host failed to execute the query
+    Host,
+    /// The host result or a column value could not be decoded
+    Decode(String),
+    /// A row does not contain the requested column
+    MissingColumn(String),
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SqlError::Query(err) => write!(f, "{}", err),
+            SqlError::Host => write!(f, "Host failed to execute query."),
+            SqlError::Decode(err) => write!(f, "Problem decoding query result: {}", err),
+            SqlError::MissingColumn(column) => write!(f, "Column not found in row: {}", column),
+        }
+    }
+}
+
+impl From<QueryError> for SqlError {
+    fn from(err: QueryError) -> Self {
+        SqlError::Query(err)
+    }
+}
+
+impl From<Error> for SqlError {
+    fn from(err: Error) -> Self {
+        SqlError::Decode(err.to_string())
+    }
+}
+
+/// JSON encoded values of a single row, in column order.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct RowValues {
+    #[array(var = 2147483647)]
+    pub values: Vec<String>,
+}
+
+/// Rows and column names returned by a query.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct ResultSet {
+    #[array(var = 2147483647)]
+    pub columns: Vec<String>,
+    #[array(var = 2147483647)]
+    pub rows: Vec<RowValues>,
+}
+
+impl ResultSet {
+    /// New empty result set with the given columns
+    pub fn new(columns: Vec<String>) -> Self {
+        ResultSet {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row of JSON encoded values in column order
+    pub fn push_row(&mut self, values: Vec<String>) {
+        self.rows.push(RowValues { values });
+    }
+
+    /// Decode a result set from its XDR encoding
+    pub fn from_xdr(bytes: &[u8]) -> Result<Self, SqlError> {
+        Ok(ResultSet::read_xdr(bytes)?.0)
+    }
+
+    /// Encode the result set as XDR bytes
+    pub fn to_xdr(&self) -> Result<Vec<u8>, SqlError> {
+        let mut bytes = Vec::new();
+        self.write_xdr(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Number of rows in the result
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// True if the query matched no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Get a row by index
+    pub fn row<'a>(&'a self, idx: usize) -> Option<Row<'a>> {
+        self.rows.get(idx).map(|row| Row {
+            columns: &self.columns,
+            values: &row.values,
+        })
+    }
+
+    /// Iterate the rows of the result
+    pub fn rows<'a>(&'a self) -> impl Iterator<Item = Row<'a>> + 'a {
+        self.rows.iter().map(move |row| Row {
+            columns: &self.columns,
+            values: &row.values,
+        })
+    }
+
+    /// Map every row into a type implementing `FromRow`
+    pub fn decode<T: FromRow>(&self) -> Result<Vec<T>, SqlError> {
+        self.rows().map(|row| T::from_row(&row)).collect()
+    }
+}
+
+/// A single row of a result set.
+#[derive(Clone, Copy, Debug)]
+pub struct Row<'a> {
+    columns: &'a [String],
+    values: &'a [String],
+}
+
+impl<'a> Row<'a> {
+    /// Column names of the row
+    pub fn columns(&self) -> &'a [String] {
+        self.columns
+    }
+
+    /// JSON encoded values of the row in column order
+    pub fn values(&self) -> &'a [String] {
+        self.values
+    }
+
+    /// JSON encoded value of a column
+    pub fn value(&self, column: &str) -> Option<&'a str> {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .and_then(|idx| self.values.get(idx))
+            .map(|val| val.as_str())
+    }
+
+    /// Decode the value of a column
+    pub fn get<T: XDRIn>(&self, column: &str) -> Result<T, SqlError> {
+        let value = self
+            .value(column)
+            .ok_or_else(|| SqlError::MissingColumn(column.to_string()))?;
+        match read_json_string(value.to_string()) {
+            Ok(val) => Ok(val),
+            // 64 bit integers are JSON encoded as strings, accept plain numbers too
+            Err(err) => match json::parse(value) {
+                Ok(ref jval) if jval.is_number() => {
+                    Ok(read_json_string(format!(r#""{}""#, value.trim()))?)
+                }
+                _ => Err(err.into()),
+            },
+        }
+    }
+}
+
+/// Types that can be built from a query result row.
+///
+/// Can be derived with `#[derive(FromRow)]` from mazzaroth-rs-derive, every
+/// field is read from the column of the same name or the name given with
+/// `#[column = "name"]`.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, SqlError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        name: String,
+        age: u64,
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &Row) -> Result<Self, SqlError> {
+            Ok(User {
+                name: row.get("name")?,
+                age: row.get("age")?,
+            })
+        }
+    }
+
+    fn users() -> ResultSet {
+        let mut result = ResultSet::new(vec!["name".to_string(), "age".to_string()]);
+        result.push_row(vec![r#""bob""#.to_string(), r#""30""#.to_string()]);
+        result.push_row(vec![r#""alice""#.to_string(), "41".to_string()]);
+        result
+    }
+
+    #[test]
+    fn test_xdr_roundtrip() {
+        let result = users();
+        let bytes = result.to_xdr().unwrap();
+        assert_eq!(Ok(result), ResultSet::from_xdr(&bytes));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Ok(vec![
+                User {
+                    name: "bob".to_string(),
+                    age: 30
+                },
+                User {
+                    name: "alice".to_string(),
+                    age: 41
+                }
+            ]),
+            users().decode::<User>()
+        );
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let result = users();
+        let row = result.row(0).unwrap();
+        assert_eq!(
+            Err(SqlError::MissingColumn("email".to_string())),
+            row.get::<String>("email")
+        );
+        assert!(row.get::<u32>("name").is_err());
+        assert!(result.row(2).is_none());
+    }
+}
@@ -0,0 +1,177 @@
+//! Typed kvquery table definitions and writes.
+
+use super::query::{push_identifier, QueryError};
+use std::fmt;
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::XDROut;
+
+/// Defines the errors that can be returned when inserting, updating or deleting rows.
+///
+/// The host insert, update and delete functions return zero on success. The
+/// host ABI does not define the meaning of other return codes, they are kept
+/// as `WriteError::Unknown`. The other variants are detected before calling the
+/// host.
+#[derive(Debug, PartialEq)]
+pub enum WriteError {
+    /// The row could not be serialized to JSON
+    Encode(String),
+    /// The table name, a column name or the filter selecting rows could not be rendered
+    Query(QueryError),
+    /// The host rejected the write with a non zero return code
+    Unknown(u32),
+}
+
+impl WriteError {
+    /// Translate a non zero return code from the host write functions
+    pub fn from_code(code: u32) -> Self {
+        WriteError::Unknown(code)
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Encode(err) => write!(f, "Problem encoding row: {}", err),
+            WriteError::Query(err) => write!(f, "{}", err),
+            WriteError::Unknown(code) => write!(f, "Write failed with code {}.", code),
+        }
+    }
+}
+
+impl From<QueryError> for WriteError {
+    fn from(err: QueryError) -> Self {
+        WriteError::Query(err)
+    }
+}
+
+impl From<Error> for WriteError {
+    fn from(err: Error) -> Self {
+        WriteError::Encode(err.to_string())
+    }
+}
+
+/// A column of a table schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    /// Name of the column
+    pub name: &'static str,
+    /// Canonical ABI type of the column, e.g. "string" or "uint64"
+    pub column_type: &'static str,
+}
+
+/// A struct that is stored as a row of a kvquery table.
+///
+/// Can be derived with `#[derive(KqTable)]` from mazzaroth-rs-derive, the table
+/// name defaults to the struct name and can be set with `#[table = "name"]`,
+/// fields are stored in the column of the same name or the name given with
+/// `#[column = "name"]`.
+///
+/// A struct declaring only some of the columns of a table can be passed to
+/// `update` as a partial patch.
+pub trait Table {
+    /// Name of the table rows are inserted into
+    fn table_name() -> &'static str;
+
+    /// Column schema of the table
+    fn columns() -> Vec<Column>;
+
+    /// Serialize the row as the JSON object passed to the host
+    fn to_json(&self) -> Result<String, WriteError>;
+}
+
+// Check that a table name can be used in queries before passing it to the host
+pub(crate) fn check_table(table_name: &str) -> Result<(), WriteError> {
+    push_identifier(&mut String::new(), table_name)?;
+    Ok(())
+}
+
+// Check that the columns declared by a table can be used in queries, as
+// the renamed columns of a derived `Table` are not checked by the compiler
+pub(crate) fn check_columns<T: Table>() -> Result<(), WriteError> {
+    let mut out = String::new();
+    for column in T::columns() {
+        push_identifier(&mut out, column.name)?;
+    }
+    Ok(())
+}
+
+/// Builds a JSON object from XDR serializable field values.
+#[derive(Default)]
+pub struct JsonObject {
+    out: Vec<u8>,
+}
+
+impl JsonObject {
+    /// New empty JSON object
+    pub fn new() -> Self {
+        JsonObject::default()
+    }
+
+    /// Append a field with the JSON encoding of value
+    pub fn field<T: XDROut>(&mut self, name: &str, value: &T) -> Result<(), Error> {
+        self.out.push(if self.out.is_empty() { b'{' } else { b',' });
+        self.out.extend(json::stringify(name).into_bytes());
+        self.out.push(b':');
+        value.write_json(&mut self.out)?;
+        Ok(())
+    }
+
+    /// Close the object and return it as a string
+    pub fn finish(mut self) -> String {
+        if self.out.is_empty() {
+            self.out.push(b'{');
+        }
+        self.out.push(b'}');
+        String::from_utf8(self.out).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_object() {
+        let mut object = JsonObject::new();
+        object.field("name", &"bob".to_string()).unwrap();
+        object.field("age", &30u64).unwrap();
+        object.field("admin", &true).unwrap();
+        assert_eq!(r#"{"name":"bob","age":"30","admin":true}"#, object.finish());
+        assert_eq!("{}", JsonObject::new().finish());
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(WriteError::Unknown(1), WriteError::from_code(1));
+        assert_eq!(WriteError::Unknown(42), WriteError::from_code(42));
+    }
+
+    struct Renamed {}
+
+    impl Table for Renamed {
+        fn table_name() -> &'static str {
+            "renamed"
+        }
+
+        fn columns() -> Vec<Column> {
+            vec![Column {
+                name: "user age",
+                column_type: "uint64",
+            }]
+        }
+
+        fn to_json(&self) -> Result<String, WriteError> {
+            Ok("{}".to_string())
+        }
+    }
+
+    #[test]
+    fn test_check_columns() {
+        assert_eq!(
+            Err(WriteError::Query(QueryError::InvalidIdentifier(
+                "user age".to_string()
+            ))),
+            check_columns::<Renamed>()
+        );
+    }
+}
@@ -1,13 +1,24 @@
 //! Provides access to transaction input and return values.
 
+use crypto::PublicKey;
+
+#[cfg(not(feature = "host-mock"))]
+use super::externs::{
+    _block_height, _block_timestamp, _fetch_channel_id, _fetch_input, _fetch_sender,
+    _fetch_transaction_hash, _input_length, _ret,
+};
 #[cfg(not(feature = "host-mock"))]
-use super::externs::{_fetch_input, _fetch_sender, _input_length, _ret, PUBLIC_KEY_LENGTH};
+use crypto::PUBLIC_KEY_LENGTH;
 
 #[cfg(feature = "host-mock")]
-pub static mut ARGS: Option<Vec<u8>> = None;
+use super::mock::{self, HostCall};
+use host::with_host;
 
-#[cfg(feature = "host-mock")]
-pub static mut SENDER: Option<Vec<u8>> = None;
+/// Length in bytes of a transaction hash
+pub const HASH_LENGTH: usize = 32;
+
+/// Length in bytes of a channel id
+pub const CHANNEL_ID_LENGTH: usize = 32;
 
 /// Get the arguments encoded from the runtime input to be supplied to contract execute
 ///
@@ -26,26 +37,23 @@ pub static mut SENDER: Option<Vec<u8>> = None;
 /// let args = transaction::arguments();
 /// let response = contract.execute(&args);
 /// ```
-#[cfg(not(feature = "host-mock"))]
 pub fn arguments() -> Vec<u8> {
+    with_host(|host| host.arguments())
+}
+
+// Raw host operations used by `HostImpl`.
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_arguments() -> Vec<u8> {
     let length = unsafe { _input_length() };
-    let mut args: Vec<u8> = Vec::with_capacity(length as usize);
-    unsafe {
-        args.set_len(length as usize);
-        _fetch_input(args.as_mut_ptr());
-    }
+    let mut args: Vec<u8> = vec![0u8; length as usize];
+    unsafe { _fetch_input(args.as_mut_ptr()) };
 
     args
 }
 
 #[cfg(feature = "host-mock")]
-pub fn arguments() -> Vec<u8> {
-    unsafe {
-        match ARGS {
-            Some(ref val) => val.clone(),
-            None => vec![],
-        }
-    }
+pub(crate) fn raw_arguments() -> Vec<u8> {
+    mock::with(|mock| mock.arguments.clone())
 }
 
 /// Return encoded bytes to the runtime
@@ -65,42 +73,157 @@ pub fn arguments() -> Vec<u8> {
 /// let response = contract.execute(&args);
 /// transaction::ret(response);
 /// ```
-#[cfg(not(feature = "host-mock"))]
 pub fn ret(values: Vec<u8>) {
+    with_host(|host| host.ret(&values))
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_ret(values: &[u8]) {
     unsafe { _ret(values.as_ptr(), values.len()) };
 }
 
 #[cfg(feature = "host-mock")]
-pub fn ret(_values: Vec<u8>) {}
+pub(crate) fn raw_ret(values: &[u8]) {
+    mock::record(HostCall::Ret(values.to_vec()));
+}
+
+/// Get the public key of the account that signed the transaction
+///
+/// # Returns
+///
+/// * `PublicKey` - The public key of the sender
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::transaction;
+/// let sender = transaction::sender();
+/// log(format!("called by {}", sender));
+/// ```
+pub fn sender() -> PublicKey {
+    with_host(|host| host.sender())
+}
 
 #[cfg(not(feature = "host-mock"))]
-pub fn sender() -> Vec<u8> {
-    let mut args: Vec<u8> = Vec::with_capacity(PUBLIC_KEY_LENGTH);
-    unsafe {
-        args.set_len(PUBLIC_KEY_LENGTH);
-        _fetch_sender(args.as_mut_ptr());
-    }
+pub(crate) fn raw_sender() -> PublicKey {
+    let mut key = [0u8; PUBLIC_KEY_LENGTH];
+    unsafe { _fetch_sender(key.as_mut_ptr()) };
 
-    args
+    PublicKey::new(key)
 }
 
 #[cfg(feature = "host-mock")]
-pub fn sender() -> Vec<u8> {
-    unsafe {
-        match SENDER {
-            Some(ref val) => val.clone(),
-            None => vec![],
-        }
-    }
+pub(crate) fn raw_sender() -> PublicKey {
+    mock::with(|mock| mock.sender)
+}
+
+/// Get the hash of the transaction being executed
+///
+/// # Returns
+///
+/// * `[u8; 32]` - The transaction hash
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::transaction;
+/// let receipt_key = transaction::hash().to_vec();
+/// ```
+pub fn hash() -> [u8; HASH_LENGTH] {
+    with_host(|host| host.hash())
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_hash() -> [u8; HASH_LENGTH] {
+    let mut hash = [0u8; HASH_LENGTH];
+    unsafe { _fetch_transaction_hash(hash.as_mut_ptr()) };
+    hash
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_hash() -> [u8; HASH_LENGTH] {
+    mock::with(|mock| mock.hash)
+}
+
+/// Get the id of the channel the transaction is executed on
+///
+/// # Returns
+///
+/// * `[u8; 32]` - The channel id
+pub fn channel_id() -> [u8; CHANNEL_ID_LENGTH] {
+    with_host(|host| host.channel_id())
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_channel_id() -> [u8; CHANNEL_ID_LENGTH] {
+    let mut channel_id = [0u8; CHANNEL_ID_LENGTH];
+    unsafe { _fetch_channel_id(channel_id.as_mut_ptr()) };
+    channel_id
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_channel_id() -> [u8; CHANNEL_ID_LENGTH] {
+    mock::with(|mock| mock.channel_id)
+}
+
+/// Get the height of the block that includes the transaction
+///
+/// # Returns
+///
+/// * `u64` - The block height
+pub fn block_height() -> u64 {
+    with_host(|host| host.block_height())
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_block_height() -> u64 {
+    unsafe { _block_height() }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_block_height() -> u64 {
+    mock::with(|mock| mock.block_height)
+}
+
+/// Get the timestamp of the block that includes the transaction
+///
+/// # Returns
+///
+/// * `u64` - The block timestamp in seconds since the unix epoch
+///
+/// # Example
+///
+/// ```ignore
+/// use mazzaroth_rs::transaction;
+/// if transaction::block_timestamp() > deadline {
+///     return false;
+/// }
+/// ```
+pub fn block_timestamp() -> u64 {
+    with_host(|host| host.block_timestamp())
+}
+
+#[cfg(not(feature = "host-mock"))]
+pub(crate) fn raw_block_timestamp() -> u64 {
+    unsafe { _block_timestamp() }
+}
+
+#[cfg(feature = "host-mock")]
+pub(crate) fn raw_block_timestamp() -> u64 {
+    mock::with(|mock| mock.block_timestamp)
 }
 
 pub trait TransactionInfo {
     fn arguments(&self) -> Vec<u8>;
     fn ret(&self, _values: &[u8]);
-    fn sender(&self) -> Vec<u8>;
+    fn sender(&self) -> PublicKey;
+    fn hash(&self) -> [u8; HASH_LENGTH];
+    fn channel_id(&self) -> [u8; CHANNEL_ID_LENGTH];
+    fn block_height(&self) -> u64;
+    fn block_timestamp(&self) -> u64;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct TransactionInfoImpl {}
 impl TransactionInfo for TransactionInfoImpl {
     fn arguments(&self) -> Vec<u8> {
@@ -109,33 +232,56 @@ impl TransactionInfo for TransactionInfoImpl {
     fn ret(&self, values: &[u8]) {
         ret(values.to_vec())
     }
-    fn sender(&self) -> Vec<u8> {
+    fn sender(&self) -> PublicKey {
         sender()
     }
-}
-impl Default for TransactionInfoImpl {
-    fn default() -> Self {
-        Self {}
+    fn hash(&self) -> [u8; HASH_LENGTH] {
+        hash()
+    }
+    fn channel_id(&self) -> [u8; CHANNEL_ID_LENGTH] {
+        channel_id()
+    }
+    fn block_height(&self) -> u64 {
+        block_height()
+    }
+    fn block_timestamp(&self) -> u64 {
+        block_timestamp()
     }
 }
 
 #[cfg(test)]
 #[cfg(feature = "host-mock")]
 mod tests {
+    use super::super::mock::MockHost;
     use super::*;
 
     #[test]
     fn test_sender() {
-        unsafe { SENDER = Some(vec![3, 3, 3]) };
-        assert_eq!(vec![3, 3, 3], sender());
+        let _mock = MockHost::new().sender(PublicKey::new([3u8; 32])).install();
+        assert_eq!(PublicKey::new([3u8; 32]), sender());
     }
 
     #[test]
     fn test_arguments() {
-        unsafe { ARGS = Some(vec![4, 4, 4]) };
+        let _mock = MockHost::new().arguments(vec![4, 4, 4]).install();
         assert_eq!(vec![4, 4, 4], arguments());
     }
 
+    #[test]
+    fn test_context() {
+        let _mock = MockHost::new()
+            .hash([5u8; HASH_LENGTH])
+            .channel_id([6u8; CHANNEL_ID_LENGTH])
+            .block_height(42)
+            .block_timestamp(1_600_000_000)
+            .install();
+        let info = TransactionInfoImpl::default();
+        assert_eq!([5u8; HASH_LENGTH], info.hash());
+        assert_eq!([6u8; CHANNEL_ID_LENGTH], info.channel_id());
+        assert_eq!(42, info.block_height());
+        assert_eq!(1_600_000_000, info.block_timestamp());
+    }
+
     #[test]
     fn test_ret() {
         assert_eq!((), ret(vec![]));
@@ -0,0 +1,393 @@
+//! Host operations behind a trait, so contracts can run against an injected host.
+//!
+//! `Host` bundles the transaction, persistence, sql, account and log operations
+//! the host provides. `HostImpl` implements it over the host externs, or the
+//! mock host with the `host-mock` feature, and is the host of every contract
+//! created with `new`.
+//!
+//! The functions of the `external` modules and everything built on them reach
+//! the host through `with_host`. The wrapper generated by `mazzaroth_abi`
+//! installs its host for the duration of `execute`, so a contract can be unit
+//! tested with a fake host passed to `with_host` of the generated wrapper.
+//! Quotas, value size limits, storage usage and the checks of table and
+//! column names stay in the `external` functions, a host only implements the
+//! raw operations.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::external::persistence;
+//!
+//! impl CounterContract for Counter {
+//!     fn increment(&mut self) -> u64 {
+//!         let count = match persistence::get(b"count".to_vec()) {
+//!             Ok(bytes) => bytes[0] as u64 + 1,
+//!             Err(_) => 1,
+//!         };
+//!         persistence::store(b"count".to_vec(), vec![count as u8]).unwrap();
+//!         count
+//!     }
+//! }
+//!
+//! // In a test, with a `FakeHost` implementing `Host`
+//! let mut contract = Counter::with_host(Counter {}, FakeHost::default());
+//! contract.execute(&call);
+//! assert_eq!(Some(&vec![1]), contract.host().state.get(&b"count".to_vec()));
+//! ```
+
+use super::crypto::PublicKey;
+use super::external::sql::{self, Page, ResultSet, SqlError, WriteError};
+use super::external::transaction::{self, TransactionInfo, CHANNEL_ID_LENGTH, HASH_LENGTH};
+use super::external::{account, log, persistence, ExternalError};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Operations a contract can request from its host.
+///
+/// The transaction operations are inherited from `TransactionInfo`. The
+/// provided methods are implemented over `get` and `query`.
+pub trait Host: TransactionInfo {
+    /// Get the value stored at a key
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>, ExternalError>;
+    /// Store a value at a key
+    fn store(&mut self, key: &[u8], value: &[u8]) -> Result<(), ExternalError>;
+    /// Delete the value stored at a key
+    fn delete(&mut self, key: &[u8]) -> Result<(), ExternalError>;
+
+    /// Get the length of the value stored at a key
+    fn get_length(&self, key: &[u8]) -> Result<u32, ExternalError> {
+        self.get(key).map(|value| value.len() as u32)
+    }
+    /// Copy the value stored at a key to the front of `buf`, returns its length
+    fn get_into(&self, key: &[u8], buf: &mut [u8]) -> Result<usize, ExternalError> {
+        let value = self.get(key)?;
+        if value.len() > buf.len() {
+            return Err(ExternalError::BufferSizeError);
+        }
+        buf[..value.len()].copy_from_slice(&value);
+        Ok(value.len())
+    }
+    /// Get `length` bytes of the value stored at a key starting at `offset`
+    fn get_range(&self, key: &[u8], offset: u32, length: u32) -> Result<Vec<u8>, ExternalError> {
+        let value = self.get(key)?;
+        let start = offset as usize;
+        match start.checked_add(length as usize) {
+            Some(end) if end <= value.len() => Ok(value[start..end].to_vec()),
+            _ => Err(ExternalError::RangeError),
+        }
+    }
+
+    /// Execute a raw query and decode the result into rows
+    fn query(&self, query: &str) -> Result<ResultSet, SqlError>;
+    /// Execute a raw query and return the result bytes, `None` if there is no result
+    fn query_bytes(&self, query: &str) -> Option<Vec<u8>> {
+        match self.query(query) {
+            Ok(ref result) if !result.is_empty() => result.to_xdr().ok(),
+            _ => None,
+        }
+    }
+    /// Execute a raw query and return the page of rows starting at the continuation token
+    fn query_page(&self, query: &str, token: &[u8], page_size: u32) -> Result<Page, SqlError> {
+        sql::paginate(self.query(query)?, token, page_size).map_err(|_| SqlError::Host)
+    }
+    /// Insert a JSON object as a row of a table
+    fn insert(&mut self, table_name: &str, json: &str) -> Result<(), WriteError>;
+    /// Update the rows of a table matching a rendered filter, returns the number of updated rows
+    fn update(&mut self, table_name: &str, filter: &str, json: &str) -> Result<u32, WriteError>;
+    /// Delete the rows of a table matching a rendered filter, returns the number of deleted rows
+    fn delete_rows(&mut self, table_name: &str, filter: &str) -> Result<u32, WriteError>;
+
+    /// Returns true if the account is the channel owner
+    fn is_owner(&self, key: PublicKey) -> bool;
+    /// Returns the name registered for an account
+    fn account_name(&self, key: PublicKey) -> Result<String, ExternalError>;
+
+    /// Write a message to the log
+    fn log(&mut self, msg: &str);
+}
+
+/// `Host` implemented with the host externs, or the mock host with `host-mock`.
+#[derive(Clone, Default)]
+pub struct HostImpl {}
+
+impl TransactionInfo for HostImpl {
+    fn arguments(&self) -> Vec<u8> {
+        transaction::raw_arguments()
+    }
+    fn ret(&self, values: &[u8]) {
+        transaction::raw_ret(values)
+    }
+    fn sender(&self) -> PublicKey {
+        transaction::raw_sender()
+    }
+    fn hash(&self) -> [u8; HASH_LENGTH] {
+        transaction::raw_hash()
+    }
+    fn channel_id(&self) -> [u8; CHANNEL_ID_LENGTH] {
+        transaction::raw_channel_id()
+    }
+    fn block_height(&self) -> u64 {
+        transaction::raw_block_height()
+    }
+    fn block_timestamp(&self) -> u64 {
+        transaction::raw_block_timestamp()
+    }
+}
+
+impl Host for HostImpl {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>, ExternalError> {
+        persistence::raw_get(key)
+    }
+    fn store(&mut self, key: &[u8], value: &[u8]) -> Result<(), ExternalError> {
+        persistence::raw_store(key, value);
+        Ok(())
+    }
+    fn delete(&mut self, key: &[u8]) -> Result<(), ExternalError> {
+        persistence::raw_delete(key)
+    }
+    fn get_length(&self, key: &[u8]) -> Result<u32, ExternalError> {
+        persistence::raw_get_length(key)
+    }
+    fn get_into(&self, key: &[u8], buf: &mut [u8]) -> Result<usize, ExternalError> {
+        persistence::raw_get_into(key, buf)
+    }
+    fn get_range(&self, key: &[u8], offset: u32, length: u32) -> Result<Vec<u8>, ExternalError> {
+        persistence::raw_get_range(key, offset, length)
+    }
+
+    fn query(&self, query: &str) -> Result<ResultSet, SqlError> {
+        sql::raw_query(query)
+    }
+    fn query_bytes(&self, query: &str) -> Option<Vec<u8>> {
+        sql::raw_query_bytes(query)
+    }
+    fn query_page(&self, query: &str, token: &[u8], page_size: u32) -> Result<Page, SqlError> {
+        sql::raw_query_page(query, token, page_size)
+    }
+    fn insert(&mut self, table_name: &str, json: &str) -> Result<(), WriteError> {
+        sql::raw_insert(table_name, json)
+    }
+    fn update(&mut self, table_name: &str, filter: &str, json: &str) -> Result<u32, WriteError> {
+        sql::raw_update(table_name, filter, json)
+    }
+    fn delete_rows(&mut self, table_name: &str, filter: &str) -> Result<u32, WriteError> {
+        sql::raw_delete(table_name, filter)
+    }
+
+    fn is_owner(&self, key: PublicKey) -> bool {
+        account::raw_is_owner(key)
+    }
+    fn account_name(&self, key: PublicKey) -> Result<String, ExternalError> {
+        account::raw_name(key)
+    }
+
+    fn log(&mut self, msg: &str) {
+        log::raw_log(msg)
+    }
+}
+
+thread_local! {
+    static HOSTS: RefCell<Vec<Rc<RefCell<dyn Host>>>> = RefCell::new(Vec::new());
+}
+
+// Removes the host installed by `scoped` when the scope ends, also when it unwinds
+struct Pop;
+
+impl Drop for Pop {
+    fn drop(&mut self) {
+        HOSTS.with(|hosts| hosts.borrow_mut().pop());
+    }
+}
+
+/// Run `f` with `host` installed as the current host of this thread.
+///
+/// Called by the generated wrapper around `execute`, scopes can be nested and
+/// the previous host is restored when `f` returns.
+pub fn scoped<R, F: FnOnce() -> R>(host: Rc<RefCell<dyn Host>>, f: F) -> R {
+    HOSTS.with(|hosts| hosts.borrow_mut().push(host));
+    let _pop = Pop;
+    f()
+}
+
+/// Run `f` with the current host of this thread.
+///
+/// Outside of `scoped` the current host is a `HostImpl`.
+///
+/// # Panics
+///
+/// If called from within `f` of another `with_host`, the host can not be
+/// borrowed twice. The `external` functions call `with_host` themselves, so
+/// they can not be called from within `f` either.
+pub fn with_host<R, F: FnOnce(&mut dyn Host) -> R>(f: F) -> R {
+    match HOSTS.with(|hosts| hosts.borrow().last().cloned()) {
+        None => f(&mut HostImpl::default()),
+        Some(host) => match host.try_borrow_mut() {
+            Ok(mut host) => f(&mut *host),
+            Err(_) => panic!("The host is already borrowed."),
+        },
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+    use external::mock::{self, HostCall};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct FakeHost {
+        sender: PublicKey,
+        state: HashMap<Vec<u8>, Vec<u8>>,
+        logs: Vec<String>,
+    }
+
+    impl TransactionInfo for FakeHost {
+        fn arguments(&self) -> Vec<u8> {
+            vec![]
+        }
+        fn ret(&self, _values: &[u8]) {}
+        fn sender(&self) -> PublicKey {
+            self.sender
+        }
+        fn hash(&self) -> [u8; HASH_LENGTH] {
+            [0u8; HASH_LENGTH]
+        }
+        fn channel_id(&self) -> [u8; CHANNEL_ID_LENGTH] {
+            [0u8; CHANNEL_ID_LENGTH]
+        }
+        fn block_height(&self) -> u64 {
+            0
+        }
+        fn block_timestamp(&self) -> u64 {
+            0
+        }
+    }
+
+    impl Host for FakeHost {
+        fn get(&self, key: &[u8]) -> Result<Vec<u8>, ExternalError> {
+            self.state
+                .get(key)
+                .cloned()
+                .ok_or(ExternalError::MissingKeyError)
+        }
+        fn store(&mut self, key: &[u8], value: &[u8]) -> Result<(), ExternalError> {
+            self.state.insert(key.to_vec(), value.to_vec());
+            Ok(())
+        }
+        fn delete(&mut self, key: &[u8]) -> Result<(), ExternalError> {
+            self.state.remove(key);
+            Ok(())
+        }
+        fn query(&self, _query: &str) -> Result<ResultSet, SqlError> {
+            Err(SqlError::Host)
+        }
+        fn insert(&mut self, _table_name: &str, _json: &str) -> Result<(), WriteError> {
+            Ok(())
+        }
+        fn update(
+            &mut self,
+            _table_name: &str,
+            _filter: &str,
+            _json: &str,
+        ) -> Result<u32, WriteError> {
+            Ok(0)
+        }
+        fn delete_rows(&mut self, _table_name: &str, _filter: &str) -> Result<u32, WriteError> {
+            Ok(0)
+        }
+        fn is_owner(&self, key: PublicKey) -> bool {
+            key == self.sender
+        }
+        fn account_name(&self, _key: PublicKey) -> Result<String, ExternalError> {
+            Err(ExternalError::MissingAccountNameError)
+        }
+        fn log(&mut self, msg: &str) {
+            self.logs.push(msg.to_string());
+        }
+    }
+
+    fn fake(sender: PublicKey) -> Rc<RefCell<FakeHost>> {
+        Rc::new(RefCell::new(FakeHost {
+            sender,
+            ..FakeHost::default()
+        }))
+    }
+
+    #[test]
+    fn test_scoped_host() {
+        let host = fake(PublicKey::new([9u8; 32]));
+        let sender = scoped(host.clone(), || {
+            with_host(|host| {
+                host.store(b"key", b"value").unwrap();
+                host.log("stored");
+                host.sender()
+            })
+        });
+        assert_eq!(PublicKey::new([9u8; 32]), sender);
+        assert_eq!(Ok(b"value".to_vec()), host.borrow().get(b"key"));
+        assert_eq!(vec!["stored".to_string()], host.borrow().logs);
+    }
+
+    #[test]
+    fn test_external_functions() {
+        let alice = PublicKey::new([9u8; 32]);
+        let host = fake(alice);
+        scoped(host.clone(), || {
+            assert_eq!(alice, transaction::sender());
+            assert!(account::is_owner(alice));
+            persistence::store(b"key".to_vec(), b"value".to_vec()).unwrap();
+            assert_eq!(Ok(b"value".to_vec()), persistence::get(b"key".to_vec()));
+            assert_eq!(
+                Ok(b"al".to_vec()),
+                persistence::get_range(b"key".to_vec(), 1, 2)
+            );
+            log("stored".to_string());
+            assert_eq!(
+                Err(SqlError::Host),
+                sql::query_raw("SELECT * FROM t".to_string())
+            );
+        });
+
+        let host = host.borrow();
+        assert_eq!(Some(&b"value".to_vec()), host.state.get(&b"key"[..]));
+        // without a quota only the value is written
+        assert_eq!(1, host.state.len());
+        assert_eq!(vec!["stored".to_string()], host.logs);
+        // nothing reached the mock host
+        assert!(mock::calls().is_empty());
+        assert_eq!(
+            None,
+            mock::with(|mock| mock.stored(b"key").map(|v| v.to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_default_host() {
+        persistence::store(b"key".to_vec(), b"value".to_vec()).unwrap();
+        mock::assert_called(&HostCall::Store {
+            key: b"key".to_vec(),
+            value: b"value".to_vec(),
+        });
+    }
+
+    #[test]
+    fn test_nested_scopes() {
+        let outer = fake(PublicKey::default());
+        let inner = fake(PublicKey::default());
+        scoped(outer.clone(), || {
+            scoped(inner.clone(), || with_host(|host| host.log("inner")));
+            with_host(|host| host.log("outer"));
+        });
+        assert_eq!(vec!["outer".to_string()], outer.borrow().logs);
+        assert_eq!(vec!["inner".to_string()], inner.borrow().logs);
+    }
+
+    #[test]
+    #[should_panic(expected = "The host is already borrowed.")]
+    fn test_borrowed_twice() {
+        let host = fake(PublicKey::default());
+        scoped(host, || with_host(|_| with_host(|_| ())));
+    }
+}
@@ -81,7 +81,34 @@ pub use contract::{ContractError, ContractInterface};
 #[macro_use]
 extern crate cfg_if;
 
+#[macro_use]
+extern crate json;
 extern crate mazzaroth_xdr;
 extern crate xdr_rs_serialize;
+#[macro_use]
+extern crate xdr_rs_serialize_derive;
+
+/// Hash functions and other cryptographic primitives.
+pub mod crypto;
+pub use crypto::{Address, PublicKey};
 
 pub mod external;
+
+/// Host operations behind a trait, for contracts run against an injected host.
+pub mod host;
+pub use host::Host;
+
+/// Role based access control stored in contract state.
+pub mod access;
+
+/// M-of-N approval of contract calls stored in contract state.
+pub mod multisig;
+
+/// Per account nonces protecting signed payloads against replays.
+pub mod nonce;
+
+pub mod schema;
+
+/// Harness calling contracts end-to-end against the mock host.
+#[cfg(feature = "host-mock")]
+pub mod testing;
@@ -0,0 +1,578 @@
+//! M-of-N approval of contract calls.
+//!
+//! A `Multisig` stores a `Policy`, a set of signers and the number of their
+//! approvals a call needs, under its name in contract state. Signers propose
+//! calls and other signers approve them. The approval that reaches the
+//! threshold, or the proposal if the threshold is 1, runs the stored call
+//! with the handler passed by the contract.
+//!
+//! The handler is a function of the contract, not one of its exported ABI
+//! functions, so the guarded operation can only be reached through the
+//! multisig. While it runs, `transaction::sender()` is the signer whose
+//! approval reached the threshold; the handler is authorized by the approvals
+//! of the `Proposal` it receives, not by the sender.
+//!
+//! Proposals expire `ttl` blocks after they were made, and when the policy
+//! changes, so stale approvals are never counted against a new signer set.
+//! Expired proposals can be removed with `prune`.
+//!
+//! `configure` performs no authorization, guard it like any other privileged
+//! contract function, e.g. with `access::require_owner`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::multisig::{Multisig, Proposal};
+//!
+//! const TREASURY: Multisig = Multisig::new("treasury");
+//!
+//! // Not part of the contract ABI, only reachable through the multisig
+//! fn run(proposal: &Proposal) -> Result<Vec<u8>, ContractError> {
+//!     let mut decoder = InputDecoder::new(&proposal.call.arguments);
+//!     match proposal.call.function.as_str() {
+//!         "transfer" => {
+//!             let to = decoder.pop::<PublicKey>("PublicKey")?;
+//!             let amount = decoder.pop::<u64>("u64")?;
+//!             transfer(to, amount);
+//!             Ok(vec![])
+//!         }
+//!         _ => Err(ContractError::invalid_function()),
+//!     }
+//! }
+//!
+//! impl TreasuryContract for Treasury {
+//!     fn propose_transfer(&mut self, to: PublicKey, amount: u64) -> u64 {
+//!         let call = Call {
+//!             function: "transfer".to_string(),
+//!             arguments: vec![Argument { t: format!("\"{}\"", to) }, ...],
+//!         };
+//!         TREASURY.propose(call, run).unwrap().id
+//!     }
+//!
+//!     fn approve_transfer(&mut self, id: u64) {
+//!         TREASURY.approve(id, run).unwrap();
+//!     }
+//! }
+//! ```
+
+use super::crypto::PublicKey;
+use super::external::{persistence, transaction, ExternalError};
+use super::schema::{self, SchemaError};
+use super::ContractError;
+
+use json;
+use mazzaroth_xdr::Call;
+use std::fmt;
+use std::io::Write;
+use xdr_rs_serialize::de::{read_var_array, read_var_array_json, XDRIn};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{write_var_array, write_var_array_json, XDROut};
+
+const PREFIX: &[u8] = b"__mazzaroth_multisig/";
+
+/// Defines the errors that can be returned by multisig operations.
+#[derive(Debug, PartialEq)]
+pub enum MultisigError {
+    /// No policy has been configured for the multisig
+    NotConfigured,
+    /// The threshold is zero or larger than the number of signers, or a signer is repeated
+    InvalidPolicy,
+    /// The sender is not one of the signers
+    NotSigner(PublicKey),
+    /// No proposal with the id exists
+    UnknownProposal(u64),
+    /// The proposal expired before reaching the threshold
+    Expired(u64),
+    /// The proposal already reached the threshold
+    AlreadyExecuted(u64),
+    /// The handler failed to run the call, the proposal is still pending
+    Call(String),
+    /// Stored multisig state could not be encoded or decoded
+    Codec(String),
+    /// Reading or writing state failed
+    External(ExternalError),
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MultisigError::NotConfigured => write!(f, "Multisig policy is not configured."),
+            MultisigError::InvalidPolicy => write!(f, "Invalid multisig signers or threshold."),
+            MultisigError::NotSigner(account) => {
+                write!(f, "Account {} is not a multisig signer.", account)
+            }
+            MultisigError::UnknownProposal(id) => write!(f, "Proposal {} does not exist.", id),
+            MultisigError::Expired(id) => write!(f, "Proposal {} has expired.", id),
+            MultisigError::AlreadyExecuted(id) => {
+                write!(f, "Proposal {} was already executed.", id)
+            }
+            MultisigError::Call(err) => write!(f, "Proposed call failed: {}", err),
+            MultisigError::Codec(err) => write!(f, "Problem encoding multisig state: {}", err),
+            MultisigError::External(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ExternalError> for MultisigError {
+    fn from(err: ExternalError) -> Self {
+        MultisigError::External(err)
+    }
+}
+
+impl From<SchemaError> for MultisigError {
+    fn from(err: SchemaError) -> Self {
+        MultisigError::Codec(err.to_string())
+    }
+}
+
+/// Signers of a multisig and the approvals required to execute a proposal.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct Policy {
+    /// Accounts allowed to propose and approve calls
+    #[array(var = 2147483647)]
+    pub signers: Vec<PublicKey>,
+    /// Number of signer approvals needed to execute a proposal
+    pub threshold: u32,
+    /// Number of blocks after which a pending proposal expires, 0 to never expire
+    pub ttl: u64,
+}
+
+impl Policy {
+    fn is_valid(&self) -> bool {
+        let mut signers = self.signers.clone();
+        signers.sort();
+        signers.dedup();
+        signers.len() == self.signers.len()
+            && self.threshold > 0
+            && self.threshold as usize <= self.signers.len()
+    }
+}
+
+/// Policy and counters of a multisig as stored in state.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+struct Wallet {
+    policy: Policy,
+    version: u32,
+    next_id: u64,
+}
+
+/// A proposed call and the signers that approved it.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct Proposal {
+    /// Id of the proposal
+    pub id: u64,
+    /// The call executed once the threshold is reached
+    pub call: Call,
+    /// Signer that made the proposal
+    pub proposer: PublicKey,
+    /// Signers that approved the proposal, including the proposer
+    #[array(var = 2147483647)]
+    pub approvals: Vec<PublicKey>,
+    /// Policy version the proposal was made under
+    pub version: u32,
+    /// Block height the proposal was made at
+    pub block_height: u64,
+    /// True once the threshold was reached and the call ran
+    pub executed: bool,
+}
+
+/// Result of a proposal or approval.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Outcome {
+    /// Id of the proposal
+    pub id: u64,
+    /// Values returned by the handler if the call ran, `None` while the
+    /// proposal needs more approvals
+    pub result: Option<Vec<u8>>,
+}
+
+/// A named multisig stored in contract state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Multisig {
+    name: &'static str,
+}
+
+impl Multisig {
+    /// Multisig stored under name, a contract can hold several multisigs with different names
+    pub const fn new(name: &'static str) -> Self {
+        Multisig { name }
+    }
+
+    fn key(&self, suffix: &[u8]) -> Vec<u8> {
+        let mut key = PREFIX.to_vec();
+        key.extend_from_slice(self.name.as_bytes());
+        key.push(b'/');
+        key.extend_from_slice(suffix);
+        key
+    }
+
+    fn proposal_key(&self, id: u64) -> Vec<u8> {
+        let mut suffix = b"proposal/".to_vec();
+        suffix.extend_from_slice(&id.to_be_bytes());
+        self.key(&suffix)
+    }
+
+    fn wallet(&self) -> Result<Wallet, MultisigError> {
+        match persistence::get(self.key(b"wallet")) {
+            Ok(bytes) => Ok(schema::decode(&bytes)?),
+            Err(ExternalError::MissingKeyError) => Err(MultisigError::NotConfigured),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn store_wallet(&self, wallet: &Wallet) -> Result<(), MultisigError> {
+        Ok(persistence::store(
+            self.key(b"wallet"),
+            schema::encode(wallet)?,
+        )?)
+    }
+
+    fn store_proposal(&self, proposal: &Proposal) -> Result<(), MultisigError> {
+        Ok(persistence::store(
+            self.proposal_key(proposal.id),
+            schema::encode(proposal)?,
+        )?)
+    }
+
+    // Returns the wallet and the sender if the sender is a signer
+    fn signer(&self) -> Result<(Wallet, PublicKey), MultisigError> {
+        let wallet = self.wallet()?;
+        let sender = transaction::sender();
+        if !wallet.policy.signers.contains(&sender) {
+            return Err(MultisigError::NotSigner(sender));
+        }
+        Ok((wallet, sender))
+    }
+
+    /// Set the signers and threshold.
+    ///
+    /// Pending proposals made under the previous policy expire.
+    pub fn configure(&self, policy: Policy) -> Result<(), MultisigError> {
+        if !policy.is_valid() {
+            return Err(MultisigError::InvalidPolicy);
+        }
+        let wallet = match self.wallet() {
+            Ok(wallet) => Wallet {
+                policy,
+                version: wallet.version + 1,
+                next_id: wallet.next_id,
+            },
+            Err(MultisigError::NotConfigured) => Wallet {
+                policy,
+                version: 0,
+                next_id: 0,
+            },
+            Err(err) => return Err(err),
+        };
+        self.store_wallet(&wallet)
+    }
+
+    /// The current policy
+    pub fn policy(&self) -> Result<Policy, MultisigError> {
+        Ok(self.wallet()?.policy)
+    }
+
+    /// Get a proposal by id
+    pub fn proposal(&self, id: u64) -> Result<Proposal, MultisigError> {
+        match persistence::get(self.proposal_key(id)) {
+            Ok(bytes) => Ok(schema::decode(&bytes)?),
+            Err(ExternalError::MissingKeyError) => Err(MultisigError::UnknownProposal(id)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns true if the proposal can no longer be approved because the
+    /// policy changed or `ttl` blocks passed since it was made
+    pub fn is_expired(&self, proposal: &Proposal) -> Result<bool, MultisigError> {
+        let wallet = self.wallet()?;
+        let ttl = wallet.policy.ttl;
+        let age = transaction::block_height().saturating_sub(proposal.block_height);
+        Ok(proposal.version != wallet.version || (ttl > 0 && age >= ttl))
+    }
+
+    /// Propose a call, approved by the sender who must be a signer.
+    ///
+    /// If the proposer's approval meets the threshold the call runs right
+    /// away with `handler`.
+    pub fn propose<F>(&self, call: Call, handler: F) -> Result<Outcome, MultisigError>
+    where
+        F: FnOnce(&Proposal) -> Result<Vec<u8>, ContractError>,
+    {
+        let (mut wallet, sender) = self.signer()?;
+        let id = wallet.next_id;
+        wallet.next_id += 1;
+        self.store_wallet(&wallet)?;
+        let proposal = Proposal {
+            id,
+            call,
+            proposer: sender,
+            approvals: vec![sender],
+            version: wallet.version,
+            block_height: transaction::block_height(),
+            executed: false,
+        };
+        self.run(&wallet, proposal, handler)
+    }
+
+    /// Approve a proposal as the sender, who must be a signer.
+    ///
+    /// The approval that reaches the threshold runs the call with `handler`
+    /// and marks the proposal executed, so the call runs once. If the handler
+    /// fails the approval is kept and the proposal stays pending, so a later
+    /// approval runs it again. Approving a proposal twice counts once.
+    pub fn approve<F>(&self, id: u64, handler: F) -> Result<Outcome, MultisigError>
+    where
+        F: FnOnce(&Proposal) -> Result<Vec<u8>, ContractError>,
+    {
+        let (wallet, sender) = self.signer()?;
+        let mut proposal = self.proposal(id)?;
+        if proposal.executed {
+            return Err(MultisigError::AlreadyExecuted(id));
+        }
+        if self.is_expired(&proposal)? {
+            return Err(MultisigError::Expired(id));
+        }
+        if !proposal.approvals.contains(&sender) {
+            proposal.approvals.push(sender);
+        }
+        self.run(&wallet, proposal, handler)
+    }
+
+    // Store the proposal, running its call first if it reached the threshold
+    fn run<F>(
+        &self,
+        wallet: &Wallet,
+        mut proposal: Proposal,
+        handler: F,
+    ) -> Result<Outcome, MultisigError>
+    where
+        F: FnOnce(&Proposal) -> Result<Vec<u8>, ContractError>,
+    {
+        let id = proposal.id;
+        if proposal.approvals.len() < wallet.policy.threshold as usize {
+            self.store_proposal(&proposal)?;
+            return Ok(Outcome { id, result: None });
+        }
+        // marked executed before the call runs so the handler can not run it again
+        proposal.executed = true;
+        self.store_proposal(&proposal)?;
+        match handler(&proposal) {
+            Ok(values) => Ok(Outcome {
+                id,
+                result: Some(values),
+            }),
+            Err(err) => {
+                proposal.executed = false;
+                self.store_proposal(&proposal)?;
+                Err(MultisigError::Call(err.to_string()))
+            }
+        }
+    }
+
+    /// Withdraw the sender's approval of a pending proposal
+    pub fn revoke(&self, id: u64) -> Result<(), MultisigError> {
+        let (_, sender) = self.signer()?;
+        let mut proposal = self.proposal(id)?;
+        if proposal.executed {
+            return Err(MultisigError::AlreadyExecuted(id));
+        }
+        proposal.approvals.retain(|approval| *approval != sender);
+        self.store_proposal(&proposal)
+    }
+
+    /// Delete a proposal from state if it has been executed or has expired.
+    ///
+    /// Returns true if the proposal was deleted. Anyone may prune proposals.
+    pub fn prune(&self, id: u64) -> Result<bool, MultisigError> {
+        let proposal = self.proposal(id)?;
+        if !proposal.executed && !self.is_expired(&proposal)? {
+            return Ok(false);
+        }
+        persistence::delete(self.proposal_key(id))?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+    use external::mock;
+
+    fn key(byte: u8) -> PublicKey {
+        PublicKey::new([byte; 32])
+    }
+
+    fn as_sender(sender: PublicKey) {
+        mock::with(|mock| mock.sender = sender);
+    }
+
+    fn at_height(block_height: u64) {
+        mock::with(|mock| mock.block_height = block_height);
+    }
+
+    fn call(function: &str) -> Call {
+        Call {
+            function: function.to_string(),
+            arguments: vec![],
+        }
+    }
+
+    // Handler returning the name of the function it ran
+    fn run(proposal: &Proposal) -> Result<Vec<u8>, ContractError> {
+        Ok(proposal.call.function.as_bytes().to_vec())
+    }
+
+    fn not_run(_proposal: &Proposal) -> Result<Vec<u8>, ContractError> {
+        panic!("the call must not run")
+    }
+
+    fn pending(id: u64) -> Result<Outcome, MultisigError> {
+        Ok(Outcome { id, result: None })
+    }
+
+    fn executed(id: u64, function: &str) -> Result<Outcome, MultisigError> {
+        Ok(Outcome {
+            id,
+            result: Some(function.as_bytes().to_vec()),
+        })
+    }
+
+    fn policy(threshold: u32, ttl: u64) -> Policy {
+        Policy {
+            signers: vec![key(60), key(61), key(62)],
+            threshold,
+            ttl,
+        }
+    }
+
+    #[test]
+    fn test_threshold() {
+        let multisig = Multisig::new("test_threshold");
+        assert_eq!(Err(MultisigError::NotConfigured), multisig.policy());
+        assert_eq!(Ok(()), multisig.configure(policy(2, 0)));
+
+        as_sender(key(60));
+        let id = multisig.propose(call("transfer"), not_run).unwrap().id;
+        assert_eq!(pending(id), multisig.approve(id, not_run));
+
+        as_sender(key(1));
+        assert_eq!(
+            Err(MultisigError::NotSigner(key(1))),
+            multisig.approve(id, not_run)
+        );
+
+        as_sender(key(61));
+        let mut approvals = 0;
+        let outcome = multisig.approve(id, |proposal| {
+            approvals = proposal.approvals.len();
+            run(proposal)
+        });
+        assert_eq!(executed(id, "transfer"), outcome);
+        assert_eq!(2, approvals);
+        assert_eq!(
+            Err(MultisigError::AlreadyExecuted(id)),
+            multisig.approve(id, not_run)
+        );
+
+        assert_eq!(Ok(true), multisig.prune(id));
+        assert_eq!(
+            Err(MultisigError::UnknownProposal(id)),
+            multisig.proposal(id)
+        );
+    }
+
+    #[test]
+    fn test_single_approval() {
+        let multisig = Multisig::new("test_single_approval");
+        multisig.configure(policy(1, 0)).unwrap();
+
+        as_sender(key(62));
+        assert_eq!(executed(0, "pause"), multisig.propose(call("pause"), run));
+        assert_eq!(Ok(true), multisig.proposal(0).map(|p| p.executed));
+    }
+
+    #[test]
+    fn test_failed_call() {
+        let multisig = Multisig::new("test_failed_call");
+        multisig.configure(policy(2, 0)).unwrap();
+
+        as_sender(key(60));
+        let id = multisig.propose(call("transfer"), not_run).unwrap().id;
+        as_sender(key(61));
+        assert_eq!(
+            Err(MultisigError::Call(
+                ContractError::invalid_function().to_string()
+            )),
+            multisig.approve(id, |_| Err(ContractError::invalid_function()))
+        );
+        assert_eq!(Ok(false), multisig.proposal(id).map(|p| p.executed));
+        assert_eq!(executed(id, "transfer"), multisig.approve(id, run));
+    }
+
+    #[test]
+    fn test_revoke() {
+        let multisig = Multisig::new("test_revoke");
+        multisig.configure(policy(2, 0)).unwrap();
+
+        as_sender(key(60));
+        let id = multisig.propose(call("upgrade"), not_run).unwrap().id;
+        assert_eq!(Ok(()), multisig.revoke(id));
+        as_sender(key(61));
+        assert_eq!(pending(id), multisig.approve(id, not_run));
+        as_sender(key(62));
+        assert_eq!(executed(id, "upgrade"), multisig.approve(id, run));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let multisig = Multisig::new("test_expiry");
+        multisig.configure(policy(2, 10)).unwrap();
+
+        as_sender(key(60));
+        at_height(100);
+        let first = multisig.propose(call("a"), not_run).unwrap().id;
+        at_height(105);
+        let second = multisig.propose(call("b"), not_run).unwrap().id;
+
+        // no later proposal is needed for a proposal to expire
+        at_height(110);
+        as_sender(key(61));
+        assert_eq!(
+            Err(MultisigError::Expired(first)),
+            multisig.approve(first, not_run)
+        );
+        assert_eq!(Ok(false), multisig.prune(second));
+        assert_eq!(executed(second, "b"), multisig.approve(second, run));
+        assert_eq!(Ok(true), multisig.prune(first));
+
+        // changing the policy expires pending proposals
+        let third = multisig.propose(call("c"), not_run).unwrap().id;
+        multisig.configure(policy(1, 0)).unwrap();
+        assert_eq!(
+            Err(MultisigError::Expired(third)),
+            multisig.approve(third, not_run)
+        );
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        let multisig = Multisig::new("test_invalid_policy");
+        assert_eq!(
+            Err(MultisigError::InvalidPolicy),
+            multisig.configure(policy(4, 0))
+        );
+        assert_eq!(
+            Err(MultisigError::InvalidPolicy),
+            multisig.configure(policy(0, 0))
+        );
+        assert_eq!(
+            Err(MultisigError::InvalidPolicy),
+            multisig.configure(Policy {
+                signers: vec![key(1), key(1)],
+                threshold: 1,
+                ttl: 0,
+            })
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Replay protection with per account nonces.
+//!
+//! Every account has a nonce stored in contract state, starting at 0. A
+//! payload carries the nonce it was signed with and is accepted only if it
+//! matches the stored nonce, which is then incremented, so the same payload
+//! can never be accepted twice.
+//!
+//! Nonces are keyed by `transaction::sender()` with `consume_nonce`, or by the
+//! public key that signed a payload with `consume_signer_nonce`, for payloads
+//! relayed by another sender. Sender and signer nonces are counted separately,
+//! so an account has one nonce as a sender and another as a signer.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::{crypto, nonce};
+//!
+//! fn relay(&mut self, signer: PublicKey, nonce: u64, payload: Vec<u8>, signature: Vec<u8>) {
+//!     let mut message = nonce.to_be_bytes().to_vec();
+//!     message.extend(&payload);
+//!     assert!(crypto::verify(signer.as_ref(), &message, &signature).unwrap());
+//!     nonce::consume_signer_nonce(signer, nonce).unwrap();
+//!     // ...
+//! }
+//! ```
+
+use super::crypto::PublicKey;
+use super::external::{persistence, transaction, ExternalError};
+
+use std::fmt;
+
+const SENDER_PREFIX: &[u8] = b"__mazzaroth_nonce/sender/";
+
+const SIGNER_PREFIX: &[u8] = b"__mazzaroth_nonce/signer/";
+
+/// Defines the errors that can be returned when consuming a nonce.
+#[derive(Debug, PartialEq)]
+pub enum NonceError {
+    /// The nonce was already consumed, `expected` is the next valid nonce
+    Stale { expected: u64, found: u64 },
+    /// The nonce is ahead of the next valid nonce `expected`
+    OutOfOrder { expected: u64, found: u64 },
+    /// The account consumed every nonce up to `u64::MAX`
+    Exhausted,
+    /// Reading or writing the stored nonce failed, a stored nonce that is not
+    /// 8 bytes long is reported as `ExternalError::DecodeError`
+    External(ExternalError),
+}
+
+impl fmt::Display for NonceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NonceError::Stale { expected, found } => write!(
+                f,
+                "Nonce {} was already used, expected {}.",
+                found, expected
+            ),
+            NonceError::OutOfOrder { expected, found } => {
+                write!(f, "Nonce {} is out of order, expected {}.", found, expected)
+            }
+            NonceError::Exhausted => write!(f, "No nonce is left."),
+            NonceError::External(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ExternalError> for NonceError {
+    fn from(err: ExternalError) -> Self {
+        NonceError::External(err)
+    }
+}
+
+fn nonce_key(prefix: &[u8], account: PublicKey) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(account.as_bytes());
+    key
+}
+
+/// Returns the next valid sender nonce of an account, 0 if it never consumed one.
+pub fn nonce(account: PublicKey) -> Result<u64, NonceError> {
+    read(nonce_key(SENDER_PREFIX, account))
+}
+
+/// Returns the next valid signer nonce of an account, 0 if it never consumed one.
+pub fn signer_nonce(account: PublicKey) -> Result<u64, NonceError> {
+    read(nonce_key(SIGNER_PREFIX, account))
+}
+
+fn read(key: Vec<u8>) -> Result<u64, NonceError> {
+    match persistence::get(key) {
+        Ok(bytes) if bytes.len() == 8 => {
+            let mut nonce = [0u8; 8];
+            nonce.copy_from_slice(&bytes);
+            Ok(u64::from_be_bytes(nonce))
+        }
+        Ok(_) => Err(ExternalError::DecodeError.into()),
+        Err(ExternalError::MissingKeyError) => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Consume the next nonce of the transaction sender.
+///
+/// # Arguments
+///
+/// * `expected` - The nonce carried by the payload
+///
+/// # Returns
+///
+/// Result<(), NonceError>
+/// * `Ok(())` - the nonce was valid and the stored nonce was incremented
+/// * `Err(NonceError)` - the nonce is stale or out of order, the stored nonce is unchanged
+pub fn consume_nonce(expected: u64) -> Result<(), NonceError> {
+    consume(nonce_key(SENDER_PREFIX, transaction::sender()), expected)
+}
+
+/// Consume the next nonce of the account that signed a payload.
+///
+/// Same as `consume_nonce` for an account other than the sender, signer
+/// nonces are counted separately from sender nonces.
+pub fn consume_signer_nonce(account: PublicKey, expected: u64) -> Result<(), NonceError> {
+    consume(nonce_key(SIGNER_PREFIX, account), expected)
+}
+
+fn consume(key: Vec<u8>, expected: u64) -> Result<(), NonceError> {
+    let current = read(key.clone())?;
+    if expected < current {
+        return Err(NonceError::Stale {
+            expected: current,
+            found: expected,
+        });
+    }
+    if expected > current {
+        return Err(NonceError::OutOfOrder {
+            expected: current,
+            found: expected,
+        });
+    }
+    let next = current.checked_add(1).ok_or(NonceError::Exhausted)?;
+    persistence::store(key, next.to_be_bytes().to_vec())?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+    use external::mock::MockHost;
+
+    #[test]
+    fn test_consume_nonce() {
+        let sender = PublicKey::new([70u8; 32]);
+        let _mock = MockHost::new().sender(sender).install();
+
+        assert_eq!(Ok(0), nonce(sender));
+        assert_eq!(Ok(()), consume_nonce(0));
+        assert_eq!(Ok(()), consume_nonce(1));
+        assert_eq!(
+            Err(NonceError::Stale {
+                expected: 2,
+                found: 1
+            }),
+            consume_nonce(1)
+        );
+        assert_eq!(
+            Err(NonceError::OutOfOrder {
+                expected: 2,
+                found: 5
+            }),
+            consume_nonce(5)
+        );
+        assert_eq!(Ok(2), nonce(sender));
+    }
+
+    #[test]
+    fn test_signer_nonce() {
+        let signer = PublicKey::new([71u8; 32]);
+        let other = PublicKey::new([72u8; 32]);
+        assert_eq!(Ok(()), consume_signer_nonce(signer, 0));
+        assert_eq!(Ok(1), signer_nonce(signer));
+        assert_eq!(Ok(0), signer_nonce(other));
+    }
+
+    #[test]
+    fn test_separate_namespaces() {
+        let account = PublicKey::new([73u8; 32]);
+        let _mock = MockHost::new().sender(account).install();
+
+        assert_eq!(Ok(()), consume_nonce(0));
+        assert_eq!(Ok(()), consume_signer_nonce(account, 0));
+        assert_eq!(Ok(1), nonce(account));
+        assert_eq!(Ok(1), signer_nonce(account));
+    }
+
+    #[test]
+    fn test_invalid_stored_nonce() {
+        let account = PublicKey::new([74u8; 32]);
+        let _mock = MockHost::new()
+            .sender(account)
+            .state(&nonce_key(SENDER_PREFIX, account), &[1, 2, 3])
+            .state(&nonce_key(SIGNER_PREFIX, account), &u64::MAX.to_be_bytes())
+            .install();
+
+        assert_eq!(
+            Err(NonceError::External(ExternalError::DecodeError)),
+            consume_nonce(0)
+        );
+        assert_eq!(
+            Err(NonceError::Exhausted),
+            consume_signer_nonce(account, u64::MAX)
+        );
+    }
+}
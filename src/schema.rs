@@ -0,0 +1,396 @@
+//! Versioned storage of XDR encoded values in contract state.
+//!
+//! Values stored through this module are wrapped in a `VersionedValue`
+//! envelope that records the schema version of the type that wrote them.
+//! When the layout of a stored type changes, bump its `VERSION` and declare
+//! a migration from the previous version so existing values can be upgraded
+//! in place, usually from a `#[migrate]` function of the contract.
+//!
+//! Values written without an envelope, before a type was versioned, are read
+//! as version `LEGACY_VERSION` and upgraded by the migration from version 0.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::schema::{self, SchemaError};
+//! use mazzaroth_rs_derive::Versioned;
+//!
+//! // Version 0 stored only a name without an envelope, version 1 adds an age.
+//! #[derive(XDRIn, XDROut, Versioned)]
+//! #[version = 1]
+//! #[migration(from = 0, with = "profile_v0_to_v1")]
+//! struct Profile {
+//!     name: String,
+//!     age: u64,
+//! }
+//!
+//! fn profile_v0_to_v1(bytes: Vec<u8>) -> Result<Vec<u8>, SchemaError> {
+//!     let name: String = schema::decode(&bytes)?;
+//!     schema::encode(&Profile { name, age: 0 })
+//! }
+//!
+//! let profile: Profile = schema::migrate(b"profile".to_vec())?;
+//! ```
+
+use super::external::{persistence, ExternalError};
+
+use json;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use xdr_rs_serialize::de::{read_var_opaque, read_var_opaque_json, XDRIn};
+use xdr_rs_serialize::error::Error;
+use xdr_rs_serialize::ser::{write_var_opaque, write_var_opaque_json, XDROut};
+
+/// A type that is stored in state with a schema version.
+///
+/// Usually derived with `#[derive(Versioned)]` from `mazzaroth_rs_derive`,
+/// which declares the version with `#[version = N]` and every migration with
+/// `#[migration(from = N, with = "function")]`.
+pub trait Versioned: XDRIn + XDROut {
+    /// The schema version of the current layout of the type.
+    const VERSION: u32;
+
+    /// The migrations upgrading stored values to the current version.
+    fn migrations() -> Migrations {
+        Migrations::new()
+    }
+}
+
+/// Version of values stored without an envelope, before the type was versioned.
+pub const LEGACY_VERSION: u32 = 0;
+
+/// Prefix written before every envelope, values without it are legacy values.
+///
+/// A legacy value that starts with these bytes is read as an envelope. XDR
+/// lengths and small unsigned integers start with a zero byte, but a legacy
+/// value starting with a negative or very large integer can collide.
+pub const ENVELOPE_MAGIC: [u8; 4] = [0xff, b'M', b'Z', b'V'];
+
+/// Envelope written to state around every versioned value.
+#[derive(PartialEq, Clone, Default, Debug, XDROut, XDRIn)]
+pub struct VersionedValue {
+    /// Schema version of the encoded value.
+    pub version: u32,
+    /// XDR encoded value.
+    #[array(var = 2147483647)]
+    pub value: Vec<u8>,
+}
+
+/// A migration upgrades an XDR encoded value from version N to version N+1.
+pub type Migration = fn(Vec<u8>) -> Result<Vec<u8>, SchemaError>;
+
+/// Defines the errors that can be returned when reading or migrating versioned values.
+#[derive(Debug, PartialEq)]
+pub enum SchemaError {
+    /// An error from the underlying persistence functions
+    External(ExternalError),
+    /// A value or envelope could not be encoded or decoded
+    Codec(String),
+    /// The stored version does not match the version of the type being read
+    VersionMismatch { found: u32, expected: u32 },
+    /// No migration is registered from the given version
+    MissingMigration(u32),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::External(err) => write!(f, "{}", err),
+            SchemaError::Codec(err) => write!(f, "Problem encoding versioned value: {}", err),
+            SchemaError::VersionMismatch { found, expected } => write!(
+                f,
+                "Stored value has version {} but version {} was expected.",
+                found, expected
+            ),
+            SchemaError::MissingMigration(version) => {
+                write!(f, "No migration registered from version {}.", version)
+            }
+        }
+    }
+}
+
+impl From<ExternalError> for SchemaError {
+    fn from(err: ExternalError) -> Self {
+        SchemaError::External(err)
+    }
+}
+
+impl From<Error> for SchemaError {
+    fn from(err: Error) -> Self {
+        SchemaError::Codec(err.to_string())
+    }
+}
+
+/// XDR encode a value, used when writing migrations.
+pub fn encode<T: XDROut>(value: &T) -> Result<Vec<u8>, SchemaError> {
+    let mut bytes = Vec::new();
+    value.write_xdr(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// XDR decode a value, used when writing migrations.
+pub fn decode<T: XDRIn>(bytes: &[u8]) -> Result<T, SchemaError> {
+    Ok(T::read_xdr(bytes)?.0)
+}
+
+/// Store a value in state wrapped in an envelope with its current version.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key used to store the value in state
+/// * `value` - The value to store
+///
+/// # Returns
+///
+/// Result<(), SchemaError>
+/// * `SchemaError` - Error if the value could not be encoded
+pub fn store<T: Versioned>(key: Vec<u8>, value: &T) -> Result<(), SchemaError> {
+    let envelope = VersionedValue {
+        version: T::VERSION,
+        value: encode(value)?,
+    };
+    store_envelope(key, &envelope)
+}
+
+/// Get a versioned value from state.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key used to get the value from state
+///
+/// # Returns
+///
+/// Result<T, SchemaError>
+/// * `T` - The decoded value
+/// * `SchemaError` - Error if the key is missing, the value cannot be decoded or
+///   it was written with a different version and must be migrated first
+pub fn get<T: Versioned>(key: Vec<u8>) -> Result<T, SchemaError> {
+    let envelope = get_envelope(key)?;
+    if envelope.version != T::VERSION {
+        return Err(SchemaError::VersionMismatch {
+            found: envelope.version,
+            expected: T::VERSION,
+        });
+    }
+    decode(&envelope.value)
+}
+
+/// Get the version a value was stored with.
+pub fn version(key: Vec<u8>) -> Result<u32, SchemaError> {
+    Ok(get_envelope(key)?.version)
+}
+
+/// Upgrade the value stored at key to the current version of `T` with the
+/// migrations declared by `T` in place and return it.
+///
+/// # Arguments
+///
+/// * `key` - The Vec<u8> key of the value
+///
+/// # Returns
+///
+/// Result<T, SchemaError>
+/// * `T` - The value at the current version
+/// * `SchemaError` - Error if the key is missing, a migration is missing or fails
+pub fn migrate<T: Versioned>(key: Vec<u8>) -> Result<T, SchemaError> {
+    T::migrations().migrate(key)
+}
+
+fn get_envelope(key: Vec<u8>) -> Result<VersionedValue, SchemaError> {
+    let bytes = persistence::get(key)?;
+    if bytes.starts_with(&ENVELOPE_MAGIC) {
+        decode(&bytes[ENVELOPE_MAGIC.len()..])
+    } else {
+        Ok(VersionedValue {
+            version: LEGACY_VERSION,
+            value: bytes,
+        })
+    }
+}
+
+fn store_envelope(key: Vec<u8>, envelope: &VersionedValue) -> Result<(), SchemaError> {
+    let mut bytes = ENVELOPE_MAGIC.to_vec();
+    envelope.write_xdr(&mut bytes)?;
+    persistence::store(key, bytes)?;
+    Ok(())
+}
+
+/// Registry of migrations between consecutive versions of a stored type,
+/// returned by `Versioned::migrations`.
+#[derive(Default)]
+pub struct Migrations {
+    steps: BTreeMap<u32, Migration>,
+}
+
+impl Migrations {
+    /// New empty migration registry
+    pub fn new() -> Self {
+        Migrations::default()
+    }
+
+    /// Register the migration that upgrades values from version `from` to `from + 1`.
+    pub fn register(mut self, from: u32, migration: Migration) -> Self {
+        self.steps.insert(from, migration);
+        self
+    }
+
+    /// Apply registered migrations until the envelope reaches the target version.
+    pub fn upgrade(
+        &self,
+        mut envelope: VersionedValue,
+        target: u32,
+    ) -> Result<VersionedValue, SchemaError> {
+        if envelope.version > target {
+            return Err(SchemaError::VersionMismatch {
+                found: envelope.version,
+                expected: target,
+            });
+        }
+        while envelope.version < target {
+            let migration = self
+                .steps
+                .get(&envelope.version)
+                .ok_or(SchemaError::MissingMigration(envelope.version))?;
+            envelope = VersionedValue {
+                version: envelope.version + 1,
+                value: migration(envelope.value)?,
+            };
+        }
+        Ok(envelope)
+    }
+
+    /// Upgrade the value stored at key to the current version of `T` in place
+    /// and return it.
+    ///
+    /// Values already at the current version are returned without being rewritten.
+    pub fn migrate<T: Versioned>(&self, key: Vec<u8>) -> Result<T, SchemaError> {
+        let envelope = get_envelope(key.clone())?;
+        if envelope.version == T::VERSION {
+            return decode(&envelope.value);
+        }
+        let envelope = self.upgrade(envelope, T::VERSION)?;
+        let value = decode(&envelope.value)?;
+        store_envelope(key, &envelope)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "host-mock")]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u64);
+
+    impl XDROut for Counter {
+        fn write_xdr(&self, out: &mut Vec<u8>) -> Result<u64, Error> {
+            self.0.write_xdr(out)
+        }
+        fn write_json(&self, out: &mut Vec<u8>) -> Result<u64, Error> {
+            self.0.write_json(out)
+        }
+    }
+
+    impl XDRIn for Counter {
+        fn read_xdr(buffer: &[u8]) -> Result<(Self, u64), Error> {
+            let (val, read) = u64::read_xdr(buffer)?;
+            Ok((Counter(val), read))
+        }
+        fn read_json(jval: json::JsonValue) -> Result<Self, Error> {
+            Ok(Counter(u64::read_json(jval)?))
+        }
+    }
+
+    impl Versioned for Counter {
+        const VERSION: u32 = 2;
+
+        fn migrations() -> Migrations {
+            Migrations::new()
+                .register(0, counter_v0_to_v1)
+                .register(1, counter_v1_to_v2)
+        }
+    }
+
+    // Version 0 stored the counter as a u32 without an envelope
+    fn counter_v0_to_v1(bytes: Vec<u8>) -> Result<Vec<u8>, SchemaError> {
+        Ok(bytes)
+    }
+
+    // Version 1 stored the counter as a u32
+    fn counter_v1_to_v2(bytes: Vec<u8>) -> Result<Vec<u8>, SchemaError> {
+        let count: u32 = decode(&bytes)?;
+        encode(&(count as u64))
+    }
+
+    fn store_v1(key: Vec<u8>, count: u32) {
+        let envelope = VersionedValue {
+            version: 1,
+            value: encode(&count).unwrap(),
+        };
+        store_envelope(key, &envelope).unwrap();
+    }
+
+    #[test]
+    fn test_store_get() {
+        store(b"schema-get".to_vec(), &Counter(7)).unwrap();
+        assert_eq!(Ok(Counter(7)), get(b"schema-get".to_vec()));
+        assert_eq!(Ok(2), version(b"schema-get".to_vec()));
+    }
+
+    #[test]
+    fn test_get_old_version() {
+        store_v1(b"schema-old".to_vec(), 3);
+        assert_eq!(
+            Err(SchemaError::VersionMismatch {
+                found: 1,
+                expected: 2
+            }),
+            get::<Counter>(b"schema-old".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_migrate() {
+        store_v1(b"schema-migrate".to_vec(), 5);
+        let migrations = Migrations::new().register(1, counter_v1_to_v2);
+        assert_eq!(
+            Ok(Counter(5)),
+            migrations.migrate(b"schema-migrate".to_vec())
+        );
+        assert_eq!(Ok(Counter(5)), get(b"schema-migrate".to_vec()));
+    }
+
+    #[test]
+    fn test_migrate_declared() {
+        store_v1(b"schema-declared".to_vec(), 6);
+        assert_eq!(Ok(Counter(6)), migrate(b"schema-declared".to_vec()));
+        assert_eq!(Ok(2), version(b"schema-declared".to_vec()));
+    }
+
+    #[test]
+    fn test_legacy_value() {
+        persistence::store(b"schema-legacy".to_vec(), encode(&4u32).unwrap()).unwrap();
+        assert_eq!(Ok(LEGACY_VERSION), version(b"schema-legacy".to_vec()));
+        assert_eq!(
+            Err(SchemaError::VersionMismatch {
+                found: 0,
+                expected: 2
+            }),
+            get::<Counter>(b"schema-legacy".to_vec())
+        );
+        assert_eq!(Ok(Counter(4)), migrate(b"schema-legacy".to_vec()));
+        assert_eq!(Ok(Counter(4)), get(b"schema-legacy".to_vec()));
+    }
+
+    #[test]
+    fn test_migrate_missing() {
+        store_v1(b"schema-missing".to_vec(), 5);
+        assert_eq!(
+            Err(SchemaError::MissingMigration(1)),
+            Migrations::new().migrate::<Counter>(b"schema-missing".to_vec())
+        );
+    }
+}
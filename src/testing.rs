@@ -0,0 +1,522 @@
+//! Test harness driving contract calls end-to-end against the mock host.
+//!
+//! A `TestRuntime` owns a contract wrapper generated by `mazzaroth_abi` and
+//! the `MockHost` holding its state between calls. Each call encodes the
+//! function name and typed arguments into a `mazzaroth_xdr::Call`, runs
+//! `execute` with the mock host installed and returns a `CallResult` with the
+//! decodable return value or error, the logs and the changes to the state.
+//!
+//! A call that returns an error or panics leaves the state and tables as they
+//! were, like a failed transaction. The storage usage counters kept by
+//! `persistence` under its reserved keys are left out of the state changes
+//! and host calls of a result.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mazzaroth_rs::testing::TestRuntime;
+//!
+//! let mut runtime = TestRuntime::new(HelloWorld::new(Hello {}));
+//!
+//! let result = runtime.call("hello", ());
+//! assert_eq!(14, result.decode::<u32>());
+//! assert_eq!(vec!["Hello World!"], result.logs());
+//!
+//! // Calls made as another account, which is not the owner
+//! let result = runtime.caller(alice).owner(false).call("upgrade", ());
+//! assert!(result.is_err());
+//! ```
+
+use super::abi::encoder::Encoder;
+use super::contract::{ContractError, ContractInterface};
+use super::crypto::PublicKey;
+use super::external::mock::{HostCall, MockHost};
+use super::external::persistence;
+
+use mazzaroth_xdr::{Argument, Call};
+use std::any::{self, Any};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use xdr_rs_serialize::de::{read_json_string, XDRIn};
+use xdr_rs_serialize::ser::XDROut;
+
+/// Arguments of a contract call, implemented for tuples of XDR values.
+///
+/// `()` calls a function without arguments, `(value,)` with a single one.
+pub trait Arguments {
+    /// Encode the arguments the way `InputDecoder` decodes them
+    fn into_arguments(self) -> Vec<Argument>;
+}
+
+impl Arguments for Vec<Argument> {
+    fn into_arguments(self) -> Vec<Argument> {
+        self
+    }
+}
+
+impl Arguments for () {
+    fn into_arguments(self) -> Vec<Argument> {
+        vec![]
+    }
+}
+
+macro_rules! arguments_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: XDROut),+> Arguments for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_arguments(self) -> Vec<Argument> {
+                let ($($name,)+) = self;
+                vec![$(argument($name)),+]
+            }
+        }
+    };
+}
+
+arguments_tuple!(A);
+arguments_tuple!(A, B);
+arguments_tuple!(A, B, C);
+arguments_tuple!(A, B, C, D);
+arguments_tuple!(A, B, C, D, E);
+arguments_tuple!(A, B, C, D, E, F);
+arguments_tuple!(A, B, C, D, E, F, G);
+arguments_tuple!(A, B, C, D, E, F, G, H);
+
+// Name of a type as written in a contract trait, `String` for `alloc::string::String`
+fn type_name<T>() -> &'static str {
+    let name = any::type_name::<T>();
+    match name.find('<') {
+        Some(_) => name,
+        None => name.rsplit("::").next().unwrap_or(name),
+    }
+}
+
+/// Encode a single typed argument of a contract call.
+pub fn argument<T: XDROut>(value: T) -> Argument {
+    let mut encoder = Encoder::default();
+    encoder.push(value, type_name::<T>());
+    Argument {
+        t: String::from_utf8(encoder.values()).unwrap(),
+    }
+}
+
+/// Why a contract call failed.
+#[derive(Debug)]
+pub enum CallError {
+    /// `execute` returned an error
+    Contract(ContractError),
+    /// The contract panicked with the message
+    Panic(String),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallError::Contract(err) => write!(f, "{}", err),
+            CallError::Panic(msg) => write!(f, "Contract panicked: {}", msg),
+        }
+    }
+}
+
+/// A value of the contract state changed by a call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateChange {
+    /// Value before the call, `None` if the key did not exist
+    pub before: Option<Vec<u8>>,
+    /// Value after the call, `None` if the key was deleted
+    pub after: Option<Vec<u8>>,
+}
+
+/// The outcome of a contract call.
+#[derive(Debug)]
+pub struct CallResult {
+    result: Result<Vec<u8>, CallError>,
+    calls: Vec<HostCall>,
+    diff: BTreeMap<Vec<u8>, StateChange>,
+}
+
+impl CallResult {
+    /// Returns true if the call succeeded
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Returns true if the call failed
+    pub fn is_err(&self) -> bool {
+        self.result.is_err()
+    }
+
+    /// Returns the encoded return value, or the error of a failed call
+    pub fn result(&self) -> Result<&[u8], &CallError> {
+        self.result.as_ref().map(|values| values.as_slice())
+    }
+
+    /// Returns the error of a failed call
+    pub fn error(&self) -> Option<&CallError> {
+        self.result.as_ref().err()
+    }
+
+    /// Decode the return value.
+    ///
+    /// # Panics
+    ///
+    /// If the call failed or the return value is not a `T`.
+    #[track_caller]
+    pub fn decode<T: XDRIn>(&self) -> T {
+        let values = match self.result {
+            Ok(ref values) => String::from_utf8_lossy(values).into_owned(),
+            Err(ref err) => panic!("Call failed: {}", err),
+        };
+        let json = match type_name::<T>() {
+            "String" | "u64" | "i64" => format!(r#""{}""#, values),
+            _ => values,
+        };
+        match read_json_string(json) {
+            Ok(value) => value,
+            Err(err) => panic!("Could not decode return value: {:?}", err),
+        }
+    }
+
+    /// Returns every call the contract made to the host, in order
+    pub fn calls(&self) -> &[HostCall] {
+        &self.calls
+    }
+
+    /// Returns the messages the contract wrote with `log`, in order
+    pub fn logs(&self) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter_map(|call| match call {
+                HostCall::Log(msg) => Some(msg.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the values of the contract state changed by the call, by key
+    pub fn state_diff(&self) -> &BTreeMap<Vec<u8>, StateChange> {
+        &self.diff
+    }
+
+    /// Returns the change of the value at a key, `None` if it is unchanged
+    pub fn changed(&self, key: &[u8]) -> Option<&StateChange> {
+        self.diff.get(key)
+    }
+}
+
+/// Runs the calls of a contract against a `MockHost` kept between calls.
+pub struct TestRuntime<C: ContractInterface> {
+    contract: C,
+    mock: MockHost,
+}
+
+impl<C: ContractInterface> TestRuntime<C> {
+    /// Runtime with an empty mock host
+    pub fn new(contract: C) -> Self {
+        TestRuntime::with_mock(contract, MockHost::new())
+    }
+
+    /// Runtime starting from the state, sender and owner of a mock host
+    pub fn with_mock(contract: C, mock: MockHost) -> Self {
+        TestRuntime { contract, mock }
+    }
+
+    /// Returns the contract wrapper
+    pub fn contract(&mut self) -> &mut C {
+        &mut self.contract
+    }
+
+    /// Returns the mock host holding the state between calls
+    pub fn mock(&mut self) -> &mut MockHost {
+        &mut self.mock
+    }
+
+    /// Call a function as the sender of the mock host.
+    pub fn call<A: Arguments>(&mut self, function: &str, arguments: A) -> CallResult {
+        let (sender, owner) = (self.mock.sender, self.mock.owner);
+        self.call_as(sender, owner, function, arguments.into_arguments())
+    }
+
+    /// Prepare a call made by another account.
+    ///
+    /// The caller is not the owner unless set with `owner`.
+    pub fn caller(&mut self, sender: PublicKey) -> Caller<'_, C> {
+        Caller {
+            runtime: self,
+            sender,
+            owner: false,
+        }
+    }
+
+    fn call_as(
+        &mut self,
+        sender: PublicKey,
+        owner: bool,
+        function: &str,
+        arguments: Vec<Argument>,
+    ) -> CallResult {
+        let call = Call {
+            function: function.to_string(),
+            arguments,
+        };
+        let mut payload = Vec::new();
+        call.write_xdr(&mut payload).unwrap();
+
+        let mut mock = mem::take(&mut self.mock);
+        let (previous_sender, previous_owner) = (mock.sender, mock.owner);
+        mock.sender = sender;
+        mock.owner = owner;
+        mock.arguments = payload.clone();
+        mock.clear_calls();
+        let before = mock.state.clone();
+        let tables = mock.tables.clone();
+        // limits set and usage counted by the call end with its transaction
+        let context = mock.persistence;
+
+        let contract = &mut self.contract;
+        let (result, mut mock) = mock
+            .scoped(move || panic::catch_unwind(AssertUnwindSafe(|| contract.execute(&payload))));
+        let result = match result {
+            Ok(Ok(values)) => Ok(values),
+            Ok(Err(err)) => Err(CallError::Contract(err)),
+            Err(panic) => Err(CallError::Panic(panic_message(panic))),
+        };
+
+        // a failed call is rolled back like its transaction
+        if result.is_err() {
+            mock.state = before.clone();
+            mock.tables = tables;
+        }
+        let diff = state_diff(&before, &mock.state);
+        let calls = mem::take(&mut mock.calls)
+            .into_iter()
+            .filter(|call| match call {
+                HostCall::Store { key, .. } | HostCall::Delete { key } => {
+                    !persistence::is_reserved(key)
+                }
+                _ => true,
+            })
+            .collect();
+        mock.sender = previous_sender;
+        mock.owner = previous_owner;
+        mock.persistence = context;
+        self.mock = mock;
+
+        CallResult {
+            result,
+            calls,
+            diff,
+        }
+    }
+}
+
+/// A call made by a specific account, created with `TestRuntime::caller`.
+pub struct Caller<'a, C: ContractInterface> {
+    runtime: &'a mut TestRuntime<C>,
+    sender: PublicKey,
+    owner: bool,
+}
+
+impl<'a, C: ContractInterface> Caller<'a, C> {
+    /// Set whether the caller is the channel owner
+    pub fn owner(mut self, owner: bool) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Call a function as the caller
+    pub fn call<A: Arguments>(self, function: &str, arguments: A) -> CallResult {
+        self.runtime.call_as(
+            self.sender,
+            self.owner,
+            function,
+            arguments.into_arguments(),
+        )
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+fn state_diff(
+    before: &HashMap<Vec<u8>, Vec<u8>>,
+    after: &HashMap<Vec<u8>, Vec<u8>>,
+) -> BTreeMap<Vec<u8>, StateChange> {
+    let mut diff = BTreeMap::new();
+    for (key, value) in before {
+        if persistence::is_reserved(key) {
+            continue;
+        }
+        if after.get(key) != Some(value) {
+            diff.insert(
+                key.clone(),
+                StateChange {
+                    before: Some(value.clone()),
+                    after: after.get(key).cloned(),
+                },
+            );
+        }
+    }
+    for (key, value) in after {
+        if !before.contains_key(key) && !persistence::is_reserved(key) {
+            diff.insert(
+                key.clone(),
+                StateChange {
+                    before: None,
+                    after: Some(value.clone()),
+                },
+            );
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::decoder::{Decoder, InputDecoder};
+    use external::{account, log, transaction};
+
+    // Hand written equivalent of a wrapper generated by mazzaroth_abi
+    struct Counter {}
+
+    impl ContractInterface for Counter {
+        fn execute(&mut self, payload: &[u8]) -> Result<Vec<u8>, ContractError> {
+            let call = Decoder::new(payload).pop::<Call>()?;
+            let mut decoder = InputDecoder::new(&call.arguments);
+            let mut encoder = Encoder::default();
+            match call.function.as_str() {
+                "add" => {
+                    let amount = decoder.pop::<u64>("u64")?;
+                    let count = match persistence::get(b"count".to_vec()) {
+                        Ok(bytes) => bytes.len() as u64,
+                        Err(_) => 0,
+                    } + amount;
+                    persistence::store(b"count".to_vec(), vec![0u8; count as usize]).unwrap();
+                    log(format!("count {}", count));
+                    encoder.push(count, "u64");
+                }
+                "greet" => {
+                    let name = decoder.pop::<String>("String")?;
+                    encoder.push(format!("hello {}", name), "String");
+                }
+                "sender" => encoder.push(transaction::sender(), "PublicKey"),
+                "reset" => {
+                    if !account::is_owner(transaction::sender()) {
+                        panic!("not the owner");
+                    }
+                    persistence::delete(b"count".to_vec()).unwrap();
+                }
+                "crash" => {
+                    persistence::store(b"count".to_vec(), vec![0u8; 9]).unwrap();
+                    panic!("crashed");
+                }
+                _ => return Err(ContractError::invalid_function()),
+            }
+            Ok(encoder.values())
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        let mut runtime = TestRuntime::new(Counter {});
+
+        let result = runtime.call("add", (2u64,));
+        assert_eq!(2, result.decode::<u64>());
+        assert_eq!(vec!["count 2"], result.logs());
+        assert_eq!(
+            &[
+                HostCall::Store {
+                    key: b"count".to_vec(),
+                    value: vec![0u8; 2],
+                },
+                HostCall::Log("count 2".to_string()),
+            ][..],
+            result.calls()
+        );
+        assert_eq!(1, result.state_diff().len());
+        assert_eq!(
+            Some(&StateChange {
+                before: None,
+                after: Some(vec![0u8; 2]),
+            }),
+            result.changed(b"count")
+        );
+
+        let result = runtime.call("add", (3u64,));
+        assert_eq!(5, result.decode::<u64>());
+        assert_eq!(vec!["count 5"], result.logs());
+
+        let result = runtime.call("greet", ("bob".to_string(),));
+        assert_eq!("hello bob".to_string(), result.decode::<String>());
+        assert!(result.state_diff().is_empty());
+
+        match runtime.call("missing", ()).error() {
+            Some(CallError::Contract(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_caller() {
+        let alice = PublicKey::new([1u8; 32]);
+        let mut runtime = TestRuntime::with_mock(Counter {}, MockHost::new().state(b"count", &[0]));
+
+        assert_eq!(alice, runtime.caller(alice).call("sender", ()).decode());
+        assert_eq!(PublicKey::default(), runtime.call("sender", ()).decode());
+
+        match runtime.caller(alice).call("reset", ()).error() {
+            Some(CallError::Panic(msg)) => assert_eq!("not the owner", msg),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(Some(&[0u8][..]), runtime.mock().stored(b"count"));
+
+        let result = runtime.caller(alice).owner(true).call("reset", ());
+        assert!(result.is_ok());
+        assert_eq!(None, result.changed(b"count").unwrap().after);
+        assert_eq!(None, runtime.mock().stored(b"count"));
+    }
+
+    #[test]
+    fn test_quota() {
+        let quota = persistence::Quota {
+            per_key: Some(3),
+            per_account: None,
+        };
+        let mut runtime = TestRuntime::with_mock(Counter {}, MockHost::new().quota(quota));
+        assert!(runtime.call("add", (2u64,)).is_ok());
+        assert!(runtime.call("add", (2u64,)).is_err());
+
+        // the usage of a call ends with its transaction
+        assert_eq!(quota, runtime.mock().persistence.quota);
+        assert_eq!(
+            persistence::StorageUsage::default(),
+            runtime.mock().persistence.usage
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut runtime = TestRuntime::with_mock(Counter {}, MockHost::new().state(b"count", &[0]));
+
+        let result = runtime.call("crash", ());
+        match result.error() {
+            Some(CallError::Panic(msg)) => assert_eq!("crashed", msg),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(result.state_diff().is_empty());
+        assert_eq!(Some(&[0u8][..]), runtime.mock().stored(b"count"));
+        assert_eq!(None, runtime.mock().stored(persistence::USAGE_KEY));
+
+        // later calls start from the state before the failed call
+        assert_eq!(3, runtime.call("add", (2u64,)).decode::<u64>());
+    }
+}